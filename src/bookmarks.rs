@@ -0,0 +1,75 @@
+//! Places saved in the reader to come back to. They're kept as a plain list
+//! of references, one per line, so the file is easy to edit by hand.
+
+use crate::cache::write_atomically;
+use crate::error::{Result, VotdError};
+use std::path::{Path, PathBuf};
+
+/// The bookmarks file, most recently added last.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bookmarks {
+    path: PathBuf,
+    references: Vec<String>,
+}
+
+impl Bookmarks {
+    /// The default location of the bookmarks file, if one can be determined.
+    pub fn default_path() -> Option<PathBuf> {
+        directories::BaseDirs::new().map(|dirs| dirs.data_dir().join("votd").join("bookmarks.txt"))
+    }
+
+    /// Reads the bookmarks file at `path`; a missing file is the same as an
+    /// empty one. Blank lines and lines starting with `#` are skipped.
+    pub fn open(path: &Path) -> Result<Self> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(VotdError::Bookmarks(e)),
+        };
+        Ok(Bookmarks {
+            path: path.to_owned(),
+            references: contents
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(str::to_owned)
+                .collect(),
+        })
+    }
+
+    /// The bookmarked references, as they were written.
+    pub fn references(&self) -> &[String] {
+        &self.references
+    }
+
+    /// Bookmarks `reference`, returning false if it already was.
+    pub fn add(&mut self, reference: &str) -> bool {
+        if self.references.iter().any(|saved| saved == reference) {
+            return false;
+        }
+        self.references.push(reference.to_owned());
+        true
+    }
+
+    /// Removes the bookmark for `reference`, returning false if there
+    /// wasn't one.
+    pub fn remove(&mut self, reference: &str) -> bool {
+        let before = self.references.len();
+        self.references.retain(|saved| saved != reference);
+        self.references.len() != before
+    }
+
+    /// Writes the bookmarks back to their file, creating its directory if
+    /// needed.
+    pub fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).map_err(VotdError::Bookmarks)?;
+        }
+        let mut contents = String::new();
+        for reference in &self.references {
+            contents.push_str(reference);
+            contents.push('\n');
+        }
+        write_atomically(&self.path, contents.as_bytes()).map_err(VotdError::Bookmarks)
+    }
+}
@@ -0,0 +1,320 @@
+//! The books of the Bible, with the names and identifiers used to refer to
+//! them.
+
+/// A book of the Bible.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Book {
+    /// The OSIS identifier, e.g. "1John".
+    pub osis: &'static str,
+    /// The USFM (Paratext) identifier, e.g. "1JN".
+    pub usfm: &'static str,
+    /// The common English name, e.g. "1 John".
+    pub name: &'static str,
+    /// The number of chapters in the book, as usually printed in English
+    /// Bibles; see [`Canon::chapters`](crate::versification::Canon::chapters)
+    /// for canons that add chapters to Esther and Daniel.
+    pub chapters: u32,
+    /// Accepted abbreviations, lowercase and without spaces or periods, with
+    /// any ordinal written as a digit (e.g. "1jn").
+    pub abbreviations: &'static [&'static str],
+}
+
+macro_rules! books {
+    ($(($osis:literal, $usfm:literal, $name:literal, $chapters:literal, [$($abbr:literal),*])),* $(,)?) => {
+        &[$(Book {
+            osis: $osis,
+            usfm: $usfm,
+            name: $name,
+            chapters: $chapters,
+            abbreviations: &[$($abbr),*],
+        }),*]
+    };
+}
+
+/// The books of the Protestant canon, in canonical order.
+#[rustfmt::skip]
+pub const BOOKS: &[Book] = books![
+    ("Gen", "GEN", "Genesis", 50, ["gen", "ge", "gn"]),
+    ("Exod", "EXO", "Exodus", 40, ["exod", "exo", "ex", "exd"]),
+    ("Lev", "LEV", "Leviticus", 27, ["lev", "le", "lv"]),
+    ("Num", "NUM", "Numbers", 36, ["num", "nu", "nm", "nb"]),
+    ("Deut", "DEU", "Deuteronomy", 34, ["deut", "de", "dt"]),
+    ("Josh", "JOS", "Joshua", 24, ["josh", "jos", "jsh"]),
+    ("Judg", "JDG", "Judges", 21, ["judg", "jdg", "jg", "jdgs"]),
+    ("Ruth", "RUT", "Ruth", 4, ["ruth", "rth", "ru"]),
+    ("1Sam", "1SA", "1 Samuel", 31, ["1sam", "1sa", "1sm", "1s"]),
+    ("2Sam", "2SA", "2 Samuel", 24, ["2sam", "2sa", "2sm", "2s"]),
+    ("1Kgs", "1KI", "1 Kings", 22, ["1kgs", "1ki", "1kg", "1k", "1kin"]),
+    ("2Kgs", "2KI", "2 Kings", 25, ["2kgs", "2ki", "2kg", "2k", "2kin"]),
+    ("1Chr", "1CH", "1 Chronicles", 29, ["1chr", "1ch", "1chron"]),
+    ("2Chr", "2CH", "2 Chronicles", 36, ["2chr", "2ch", "2chron"]),
+    ("Ezra", "EZR", "Ezra", 10, ["ezra", "ezr"]),
+    ("Neh", "NEH", "Nehemiah", 13, ["neh", "ne"]),
+    ("Esth", "EST", "Esther", 10, ["esth", "est", "es"]),
+    ("Job", "JOB", "Job", 42, ["job", "jb"]),
+    ("Ps", "PSA", "Psalms", 150, ["ps", "psa", "psalm", "pss", "psm", "pslm"]),
+    ("Prov", "PRO", "Proverbs", 31, ["prov", "pro", "prv", "pr"]),
+    ("Eccl", "ECC", "Ecclesiastes", 12, ["eccl", "ecc", "eccles", "ec", "qoh"]),
+    ("Song", "SNG", "Song of Solomon", 8, ["song", "sng", "sos", "so", "canticles", "songofsongs", "songofsol"]),
+    ("Isa", "ISA", "Isaiah", 66, ["isa", "is"]),
+    ("Jer", "JER", "Jeremiah", 52, ["jer", "je", "jr"]),
+    ("Lam", "LAM", "Lamentations", 5, ["lam", "la"]),
+    ("Ezek", "EZK", "Ezekiel", 48, ["ezek", "eze", "ezk"]),
+    ("Dan", "DAN", "Daniel", 12, ["dan", "da", "dn"]),
+    ("Hos", "HOS", "Hosea", 14, ["hos", "ho"]),
+    ("Joel", "JOL", "Joel", 3, ["joel", "jl"]),
+    ("Amos", "AMO", "Amos", 9, ["amos", "am"]),
+    ("Obad", "OBA", "Obadiah", 1, ["obad", "ob", "oba"]),
+    ("Jonah", "JON", "Jonah", 4, ["jonah", "jnh", "jon"]),
+    ("Mic", "MIC", "Micah", 7, ["mic", "mc"]),
+    ("Nah", "NAM", "Nahum", 3, ["nah", "na"]),
+    ("Hab", "HAB", "Habakkuk", 3, ["hab", "hb"]),
+    ("Zeph", "ZEP", "Zephaniah", 3, ["zeph", "zep", "zp"]),
+    ("Hag", "HAG", "Haggai", 2, ["hag", "hg"]),
+    ("Zech", "ZEC", "Zechariah", 14, ["zech", "zec", "zc"]),
+    ("Mal", "MAL", "Malachi", 4, ["mal", "ml"]),
+    ("Matt", "MAT", "Matthew", 28, ["matt", "mat", "mt"]),
+    ("Mark", "MRK", "Mark", 16, ["mark", "mrk", "mk", "mr"]),
+    ("Luke", "LUK", "Luke", 24, ["luke", "luk", "lk"]),
+    ("John", "JHN", "John", 21, ["john", "jhn", "jn", "joh"]),
+    ("Acts", "ACT", "Acts", 28, ["acts", "act", "ac"]),
+    ("Rom", "ROM", "Romans", 16, ["rom", "ro", "rm"]),
+    ("1Cor", "1CO", "1 Corinthians", 16, ["1cor", "1co"]),
+    ("2Cor", "2CO", "2 Corinthians", 13, ["2cor", "2co"]),
+    ("Gal", "GAL", "Galatians", 6, ["gal", "ga"]),
+    ("Eph", "EPH", "Ephesians", 6, ["eph", "ephes"]),
+    ("Phil", "PHP", "Philippians", 4, ["phil", "php", "pp"]),
+    ("Col", "COL", "Colossians", 4, ["col"]),
+    ("1Thess", "1TH", "1 Thessalonians", 5, ["1thess", "1th", "1thes"]),
+    ("2Thess", "2TH", "2 Thessalonians", 3, ["2thess", "2th", "2thes"]),
+    ("1Tim", "1TI", "1 Timothy", 6, ["1tim", "1ti", "1tm"]),
+    ("2Tim", "2TI", "2 Timothy", 4, ["2tim", "2ti", "2tm"]),
+    ("Titus", "TIT", "Titus", 3, ["titus", "tit", "ti"]),
+    ("Phlm", "PHM", "Philemon", 1, ["phlm", "philem", "phm", "pm"]),
+    ("Heb", "HEB", "Hebrews", 13, ["heb"]),
+    ("Jas", "JAS", "James", 5, ["jas", "jm", "james"]),
+    ("1Pet", "1PE", "1 Peter", 5, ["1pet", "1pe", "1pt", "1p"]),
+    ("2Pet", "2PE", "2 Peter", 3, ["2pet", "2pe", "2pt", "2p"]),
+    ("1John", "1JN", "1 John", 5, ["1john", "1jn", "1jo", "1jhn", "1joh"]),
+    ("2John", "2JN", "2 John", 1, ["2john", "2jn", "2jo", "2jhn", "2joh"]),
+    ("3John", "3JN", "3 John", 1, ["3john", "3jn", "3jo", "3jhn", "3joh"]),
+    ("Jude", "JUD", "Jude", 1, ["jude", "jud", "jd"]),
+    ("Rev", "REV", "Revelation", 22, ["rev", "re", "rv", "revelations", "apocalypse"]),
+];
+
+/// The deuterocanonical books, found in the Catholic and Orthodox canons.
+/// The additions to Esther and Daniel aren't listed separately; they're
+/// extra chapters of those books (see [`Canon`](crate::versification::Canon)).
+#[rustfmt::skip]
+pub const DEUTEROCANONICAL_BOOKS: &[Book] = books![
+    ("Tob", "TOB", "Tobit", 14, ["tob", "tb", "tobit"]),
+    ("Jdt", "JDT", "Judith", 16, ["jdt", "jdth", "judith"]),
+    ("Wis", "WIS", "Wisdom of Solomon", 19, ["wis", "ws", "wisdom", "wisofsol"]),
+    ("Sir", "SIR", "Sirach", 51, ["sir", "ecclus", "ecclesiasticus"]),
+    ("Bar", "BAR", "Baruch", 6, ["bar"]),
+    ("1Macc", "1MA", "1 Maccabees", 16, ["1macc", "1mac", "1ma", "1mc"]),
+    ("2Macc", "2MA", "2 Maccabees", 15, ["2macc", "2mac", "2ma", "2mc"]),
+    ("1Esd", "1ES", "1 Esdras", 9, ["1esd", "1es", "1esdr"]),
+    ("PrMan", "MAN", "Prayer of Manasseh", 1, ["prman", "man", "prmans", "manasseh"]),
+    ("3Macc", "3MA", "3 Maccabees", 7, ["3macc", "3mac", "3ma", "3mc"]),
+    ("4Macc", "4MA", "4 Maccabees", 18, ["4macc", "4mac", "4ma", "4mc"]),
+];
+
+impl Book {
+    /// Whether references to this book can omit the chapter ("Jude 3").
+    pub fn is_single_chapter(&self) -> bool {
+        self.chapters == 1
+    }
+
+    fn matches(&self, key: &str) -> bool {
+        normalize(self.name) == key
+            || normalize(self.osis) == key
+            || normalize(self.usfm) == key
+            || self.abbreviations.contains(&key)
+    }
+}
+
+/// Every known book: the Protestant canon followed by the deuterocanon.
+pub fn all() -> impl Iterator<Item = &'static Book> {
+    BOOKS.iter().chain(DEUTEROCANONICAL_BOOKS)
+}
+
+/// Finds a book by its name, OSIS or USFM identifier, or an abbreviation,
+/// ignoring case, spaces, and periods. Ordinals may be written as digits,
+/// Roman numerals, or words ("1 John", "I Jn", "First John", "1Jn").
+pub fn find(name: &str) -> Option<&'static Book> {
+    keys(name)
+        .iter()
+        .find_map(|key| all().find(|book| book.matches(key)))
+}
+
+/// Finds the books whose name starts with `name`, for when [`find`] doesn't
+/// recognize it. A unique result can be treated as a match; several results
+/// mean `name` is ambiguous.
+pub fn find_by_prefix(name: &str) -> Vec<&'static Book> {
+    let mut found = Vec::new();
+    for key in keys(name) {
+        // Skip over an ordinal so that "1" alone doesn't match every
+        // numbered book.
+        if key.trim_start_matches(|c: char| c.is_ascii_digit()).len() < 2 {
+            continue;
+        }
+        for book in all() {
+            if normalize(book.name).starts_with(&key) && !found.contains(&book) {
+                found.push(book);
+            }
+        }
+        if !found.is_empty() {
+            break;
+        }
+    }
+    found
+}
+
+/// Books with names similar to `name`, best match first, for suggesting a
+/// correction when it isn't recognized.
+pub fn suggest(name: &str) -> Vec<&'static Book> {
+    let keys = keys(name);
+    let mut scored: Vec<(usize, &'static Book)> = all()
+        .filter_map(|book| {
+            let distance = keys
+                .iter()
+                .flat_map(|key| {
+                    std::iter::once(normalize(book.name))
+                        .chain(book.abbreviations.iter().map(|a| a.to_string()))
+                        .map(move |candidate| edit_distance(key, &candidate))
+                })
+                .min()?;
+            Some((distance, book))
+        })
+        .filter(|(distance, _)| *distance <= 2)
+        .collect();
+    scored.sort_by_key(|(distance, _)| *distance);
+    scored.into_iter().map(|(_, book)| book).take(3).collect()
+}
+
+pub(crate) fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| !c.is_whitespace() && *c != '.')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// The normalized forms `name` could be looked up by: as written, and with a
+/// leading Roman numeral or ordinal word replaced by a digit.
+fn keys(name: &str) -> Vec<String> {
+    const ORDINALS: &[(&str, &str)] = &[
+        ("iii", "3"),
+        ("ii", "2"),
+        ("i", "1"),
+        ("first", "1"),
+        ("second", "2"),
+        ("third", "3"),
+        ("1st", "1"),
+        ("2nd", "2"),
+        ("3rd", "3"),
+    ];
+    let name = name.trim();
+    let mut keys = vec![normalize(name)];
+    // The ordinal has to be separated from the rest of the name, so that
+    // e.g. "Isa" isn't read as "1 Sa".
+    if let Some(split) = name.find(|c: char| c.is_whitespace() || c == '.') {
+        let (first, rest) = name.split_at(split);
+        let first = first.to_lowercase();
+        if let Some((_, digit)) = ORDINALS.iter().find(|(word, _)| *word == first) {
+            keys.push(format!("{}{}", digit, normalize(rest)));
+        }
+    }
+    keys
+}
+
+/// The edit distance between two strings, counting insertions, deletions,
+/// substitutions, and transpositions of adjacent characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    rows[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = distance;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_books_by_any_name() {
+        for name in [
+            "1 John",
+            "1john",
+            "1JN",
+            "1Jn",
+            "I Jn",
+            "First John",
+            "1st John",
+            "1 jn.",
+        ] {
+            assert_eq!(
+                find(name).map(|book| book.osis),
+                Some("1John"),
+                "{:?}",
+                name
+            );
+        }
+        assert_eq!(find("Isa").map(|book| book.osis), Some("Isa"));
+        assert_eq!(find("Sirach").map(|book| book.osis), Some("Sir"));
+        assert_eq!(find("Hezekiah"), None);
+    }
+
+    #[test]
+    fn finds_books_by_prefix() {
+        let names = |prefix| -> Vec<&str> {
+            find_by_prefix(prefix)
+                .iter()
+                .map(|book| book.name)
+                .collect()
+        };
+        assert_eq!(names("Phile"), ["Philemon"]);
+        assert_eq!(names("Phil"), ["Philippians", "Philemon"]);
+        // An ordinal alone isn't enough to go on.
+        assert!(names("1").is_empty());
+    }
+
+    #[test]
+    fn suggests_similar_names() {
+        assert!(suggest("Jhon").iter().any(|book| book.name == "John"));
+        assert_eq!(
+            suggest("Genisis").first().map(|book| book.name),
+            Some("Genesis")
+        );
+        assert!(suggest("Zzzzzzzz").is_empty());
+    }
+
+    #[test]
+    fn abbreviations_are_normalized_and_unique() {
+        let mut seen = std::collections::HashMap::new();
+        for book in all() {
+            for &abbreviation in book.abbreviations {
+                assert_eq!(normalize(abbreviation), abbreviation);
+                if let Some(other) = seen.insert(abbreviation, book.name) {
+                    panic!("{:?} is used by {} and {}", abbreviation, other, book.name);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,1005 @@
+use crate::clock::{Clock, SystemClock, Timezone};
+use crate::error::{Result, VotdError};
+use crate::reference::Reference;
+use crate::verse::{Verse, VerseText};
+use chrono::NaiveDate;
+use chrono_tz::Tz;
+use filetime::FileTime;
+use fs4::FileExt;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// What a cached passage was looked up as.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CacheKey(String);
+
+impl CacheKey {
+    /// The verse-of-the-day in `translation`.
+    pub fn votd(translation: &str) -> Self {
+        CacheKey(format!("votd/{}", translation.to_ascii_uppercase()))
+    }
+
+    /// The verse-of-the-day for `date` in `translation`, fetched ahead of
+    /// time (see [`VerseCache::get`]).
+    pub fn votd_on(translation: &str, date: NaiveDate) -> Self {
+        CacheKey(format!(
+            "votd/{}/{}",
+            translation.to_ascii_uppercase(),
+            date
+        ))
+    }
+
+    /// The passages in `reference`, in `translation`.
+    pub fn passage(translation: &str, reference: &Reference) -> Self {
+        CacheKey(format!(
+            "passage/{}/{}",
+            translation.to_ascii_uppercase(),
+            reference
+        ))
+    }
+
+    /// Whether this is the key of a verse-of-the-day, which changes daily,
+    /// rather than of a passage, which doesn't.
+    pub fn is_votd(&self) -> bool {
+        self.0.starts_with("votd/")
+    }
+
+    /// The translation the passage was cached in.
+    pub fn translation(&self) -> &str {
+        self.0.split('/').nth(1).unwrap_or_default()
+    }
+
+    /// The date of a verse-of-the-day fetched ahead of time.
+    pub fn date(&self) -> Option<NaiveDate> {
+        self.0
+            .strip_prefix("votd/")?
+            .split('/')
+            .nth(1)?
+            .parse()
+            .ok()
+    }
+
+    /// The reference that was looked up, unless this is the verse-of-the-day.
+    pub fn reference(&self) -> Option<&str> {
+        self.0
+            .strip_prefix("passage/")?
+            .split_once('/')
+            .map(|(_, r)| r)
+    }
+}
+
+/// Whether a cache file could be read when it was opened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheHealth {
+    /// The file was empty or read without problems.
+    Ok,
+    /// Some entries couldn't be decoded and were dropped; the rest were kept.
+    Damaged { kept: usize, dropped: usize },
+    /// Nothing could be decoded, so the cache was treated as empty.
+    Unreadable,
+    /// The file was written in an older layout, and was converted.
+    Migrated { from: u16 },
+    /// The file was written by a newer version, so was treated as empty.
+    Unsupported { version: u16 },
+}
+
+/// When to show an out-of-date cached passage instead of failing, if a
+/// current one can't be fetched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Fallback {
+    /// Always fail.
+    Never,
+    /// Only if the provider couldn't be reached (see
+    /// [`VotdError::is_network`]).
+    #[default]
+    OnNetworkError,
+    /// Whatever went wrong.
+    Always,
+}
+
+impl Fallback {
+    /// Whether to fall back to the cache after `error`.
+    pub fn allows(self, error: &VotdError) -> bool {
+        match self {
+            Fallback::Never => false,
+            Fallback::OnNetworkError => error.is_network(),
+            Fallback::Always => true,
+        }
+    }
+}
+
+impl fmt::Display for Fallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Fallback::Never => "never",
+            Fallback::OnNetworkError => "on-network-error",
+            Fallback::Always => "always",
+        })
+    }
+}
+
+impl FromStr for Fallback {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, String> {
+        match s.to_ascii_lowercase().replace('_', "-").as_str() {
+            "never" => Ok(Fallback::Never),
+            "on-network-error" => Ok(Fallback::OnNetworkError),
+            "always" => Ok(Fallback::Always),
+            _ => Err(format!(
+                "unknown fallback {:?}; expected never, on-network-error, or always",
+                s
+            )),
+        }
+    }
+}
+
+/// How long after a passage was last used a cache hit records the new use.
+const USED_RESOLUTION: Duration = Duration::from_secs(60 * 60);
+
+/// The bytes every cache file starts with.
+const MAGIC: &[u8; 9] = b"VOTDCACHE";
+
+/// The version of the cache file layout, written after [`MAGIC`]. Version 1
+/// is any file from before there was a header.
+const SCHEMA_VERSION: u16 = 2;
+
+/// The length of the header: the magic bytes, the schema version, and a
+/// CRC-32 checksum of the rest of the file.
+const HEADER_LEN: usize = MAGIC.len() + 2 + 4;
+
+/// The only thing the first versions cached: the verse-of-the-day from the
+/// NET Bible, as its title and text.
+#[derive(Deserialize)]
+struct LegacyVerse {
+    title: String,
+    text: String,
+}
+
+fn encode_entries(entries: &BTreeMap<String, CacheEntry>) -> Result<Vec<u8>> {
+    let payload = rmp_serde::to_vec(entries)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+    let mut bytes = Vec::with_capacity(HEADER_LEN + payload.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&SCHEMA_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&crc32fast::hash(&payload).to_le_bytes());
+    bytes.extend_from_slice(&payload);
+    Ok(bytes)
+}
+
+/// Decodes a cache file, migrating it from an older layout if needed and
+/// salvaging what entries it can if it's damaged. `modified` is when the
+/// file was last written.
+fn decode_entries(buf: &[u8], modified: u64) -> (BTreeMap<String, CacheEntry>, CacheHealth) {
+    if buf.is_empty() {
+        return (BTreeMap::new(), CacheHealth::Ok);
+    }
+    if !buf.starts_with(MAGIC) {
+        return match migrate_v1(buf, modified) {
+            Some(entries) => (entries, CacheHealth::Migrated { from: 1 }),
+            None => (BTreeMap::new(), CacheHealth::Unreadable),
+        };
+    }
+    if buf.len() < HEADER_LEN {
+        return (BTreeMap::new(), CacheHealth::Unreadable);
+    }
+    let version = u16::from_le_bytes([buf[MAGIC.len()], buf[MAGIC.len() + 1]]);
+    let checksum = u32::from_le_bytes(
+        buf[MAGIC.len() + 2..HEADER_LEN]
+            .try_into()
+            .expect("the checksum is 4 bytes"),
+    );
+    let payload = &buf[HEADER_LEN..];
+    if version != SCHEMA_VERSION {
+        // A newer version's cache; leave it be rather than guess at it.
+        return (BTreeMap::new(), CacheHealth::Unsupported { version });
+    }
+    if crc32fast::hash(payload) == checksum {
+        if let Ok(entries) = rmp_serde::from_slice(payload) {
+            return (entries, CacheHealth::Ok);
+        }
+    }
+    salvage(payload)
+}
+
+/// Decodes whatever entries of a damaged cache still can be.
+fn salvage(payload: &[u8]) -> (BTreeMap<String, CacheEntry>, CacheHealth) {
+    let raw: BTreeMap<String, serde_json::Value> = match rmp_serde::from_slice(payload) {
+        Ok(raw) => raw,
+        Err(_) => return (BTreeMap::new(), CacheHealth::Unreadable),
+    };
+    let total = raw.len();
+    let entries: BTreeMap<String, CacheEntry> = raw
+        .into_iter()
+        .filter_map(|(key, value)| Some((key, serde_json::from_value(value).ok()?)))
+        .collect();
+    let health = CacheHealth::Damaged {
+        kept: entries.len(),
+        dropped: total - entries.len(),
+    };
+    (entries, health)
+}
+
+/// Migrates a file from before there was a header, which is either a map
+/// of entries without the header, or a single verse-of-the-day (as the
+/// per-verse [`Verse`], or as the original title and text).
+fn migrate_v1(buf: &[u8], modified: u64) -> Option<BTreeMap<String, CacheEntry>> {
+    if let Ok(entries) = rmp_serde::from_slice(buf) {
+        return Some(entries);
+    }
+    let verse = match rmp_serde::from_slice::<Verse>(buf) {
+        Ok(verse) => verse,
+        Err(_) => {
+            let legacy: LegacyVerse = rmp_serde::from_slice(buf).ok()?;
+            // Only a single verse can be split back into its parts; a longer
+            // passage is just fetched again.
+            let reference: Reference = legacy.title.parse().ok()?;
+            let verse = match reference.verses().as_slice() {
+                [verse] => *verse,
+                _ => return Some(BTreeMap::new()),
+            };
+            Verse {
+                verses: vec![VerseText {
+                    book: verse.book.name.to_owned(),
+                    chapter: verse.chapter,
+                    verse: verse.verse,
+                    text: legacy.text,
+                    styles: Vec::new(),
+                    headings: Vec::new(),
+                }],
+            }
+        }
+    };
+    let entry = CacheEntry {
+        verse,
+        written: modified,
+        used: modified,
+        date: None,
+        timezone: None,
+    };
+    Some(BTreeMap::from_iter([(CacheKey::votd("NET").0, entry)]))
+}
+
+/// A passage held in the cache.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CacheEntry {
+    pub verse: Verse,
+    /// When the passage was cached, in seconds since the Unix epoch.
+    pub written: u64,
+    /// When the passage was last read from or written to the cache, in
+    /// seconds since the Unix epoch.
+    pub used: u64,
+    /// For a verse-of-the-day, the date it was the verse-of-the-day on, if
+    /// known; otherwise it's taken to be the date `written` falls on.
+    #[serde(default)]
+    pub date: Option<NaiveDate>,
+    /// For a verse-of-the-day, the name of the timezone `date` is in.
+    #[serde(default)]
+    pub timezone: Option<String>,
+}
+
+impl CacheEntry {
+    /// When the passage was cached.
+    pub fn written_at(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.written)
+    }
+}
+
+/// Locks the cache file at `path`, shared for reading or exclusive for
+/// writing, until the returned file is dropped. The lock is taken on a
+/// separate `.lock` file, since the cache file itself is replaced when it's
+/// written.
+fn lock(path: &Path, exclusive: bool) -> Result<File> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut name = path.file_name().unwrap_or_default().to_owned();
+    name.push(".lock");
+    let file = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path.with_file_name(name))?;
+    match exclusive {
+        true => FileExt::lock_exclusive(&file)?,
+        false => FileExt::lock_shared(&file)?,
+    }
+    Ok(file)
+}
+
+/// Reads the cache file at `path`; a missing file is the same as an empty
+/// one.
+fn read_entries(path: &Path) -> Result<(BTreeMap<String, CacheEntry>, CacheHealth)> {
+    let buf = match std::fs::read(path) {
+        Ok(buf) => buf,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Ok((BTreeMap::new(), CacheHealth::Ok))
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let modified = FileTime::from_last_modification_time(&std::fs::metadata(path)?).seconds();
+    Ok(decode_entries(&buf, u64::try_from(modified).unwrap_or(0)))
+}
+
+/// Replaces the file at `path` with `bytes`, by writing them to a temporary
+/// file beside it and renaming that over it, so the file is never left half
+/// written.
+pub(crate) fn write_atomically(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    let mut name = std::ffi::OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(format!(".{}.tmp", std::process::id()));
+    let temp = path.with_file_name(name);
+    let written = File::create(&temp)
+        .and_then(|mut file| {
+            file.write_all(bytes)?;
+            file.sync_all()
+        })
+        .and_then(|()| std::fs::rename(&temp, path));
+    if written.is_err() {
+        let _ = std::fs::remove_file(&temp);
+    }
+    written
+}
+
+/// The on-disk cache of recently looked up passages, including the
+/// verse-of-the-day. Once it holds more than its maximum number of entries,
+/// the least recently used are evicted.
+///
+/// Several processes can use the same cache at once: the file is locked
+/// while it's read or written, and saving only writes back the entries this
+/// cache changed, on top of whatever the file holds by then.
+#[derive(Debug)]
+pub struct VerseCache {
+    path: PathBuf,
+    entries: BTreeMap<String, CacheEntry>,
+    /// Keys whose entries were added or updated since the cache was opened.
+    changed: BTreeSet<String>,
+    /// Keys whose entries were removed since the cache was opened.
+    removed: BTreeSet<String>,
+    health: CacheHealth,
+    max_entries: usize,
+    dirty: bool,
+    clock: Box<dyn Clock>,
+    timezone: Timezone,
+    provider_timezone: Tz,
+}
+
+impl VerseCache {
+    /// The number of passages kept by default, before the least recently
+    /// used are evicted.
+    pub const DEFAULT_MAX_ENTRIES: usize = 64;
+
+    /// The default location of the cache file, if one can be determined.
+    pub fn default_path() -> Option<PathBuf> {
+        directories::BaseDirs::new().map(|dirs| dirs.cache_dir().join("votd-cli-cache.txt"))
+    }
+
+    /// Opens the cache file at `path` (which needn't exist yet), which holds
+    /// at most `max_entries` passages. Entries that can't be decoded (e.g.
+    /// ones written by an older version) are dropped; see
+    /// [`VerseCache::health`].
+    pub fn open(path: &Path, max_entries: usize) -> Result<Self> {
+        let (entries, health) = {
+            let _lock = lock(path, false)?;
+            read_entries(path)?
+        };
+        Ok(VerseCache {
+            path: path.to_owned(),
+            entries,
+            changed: BTreeSet::new(),
+            removed: BTreeSet::new(),
+            health,
+            max_entries,
+            dirty: health != CacheHealth::Ok,
+            clock: Box::new(SystemClock),
+            timezone: Timezone::default(),
+            provider_timezone: chrono_tz::UTC,
+        })
+    }
+
+    /// Uses `clock` for the current time instead of the system clock.
+    pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    /// Starts a new verse-of-the-day at midnight in `timezone`, where the
+    /// provider's own timezone is `provider`.
+    pub fn with_timezone(mut self, timezone: Timezone, provider: Tz) -> Self {
+        self.timezone = timezone;
+        self.provider_timezone = provider;
+        self
+    }
+
+    fn now(&self) -> u64 {
+        u64::try_from(self.clock.now().timestamp()).unwrap_or(0)
+    }
+
+    /// Today's date, and the name of the timezone it's in.
+    fn today(&self) -> (NaiveDate, String) {
+        (
+            self.timezone.date(self.provider_timezone, self.clock.now()),
+            self.timezone.name(self.provider_timezone),
+        )
+    }
+
+    /// The number of passages in the cache.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the cache holds no passages.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Whether the cache file could be decoded when it was opened. A damaged
+    /// file is rewritten with what could be salvaged the next time the cache
+    /// is saved.
+    pub fn health(&self) -> CacheHealth {
+        self.health
+    }
+
+    /// The size of the cache file, in bytes.
+    pub fn file_size(&self) -> Result<u64> {
+        match std::fs::metadata(&self.path) {
+            Ok(metadata) => Ok(metadata.len()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(0),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Deletes the cache file at `path`, if there is one.
+    pub fn clear(path: &Path) -> Result<()> {
+        let _lock = lock(path, true)?;
+        match std::fs::remove_file(path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Every cached passage, by key.
+    pub fn entries(&self) -> impl Iterator<Item = (CacheKey, &CacheEntry)> {
+        self.entries
+            .iter()
+            .map(|(key, entry)| (CacheKey(key.clone()), entry))
+    }
+
+    /// Whether `entry`, cached under `key`, would still be returned by
+    /// [`VerseCache::get`].
+    pub fn is_current(&self, key: &CacheKey, entry: &CacheEntry) -> bool {
+        if !key.is_votd() {
+            return true;
+        }
+        let (today, timezone) = self.today();
+        let date = match entry.date {
+            Some(date) => date,
+            None => match chrono::DateTime::from_timestamp(entry.written as i64, 0) {
+                Some(written) => self.timezone.date(self.provider_timezone, written),
+                None => return false,
+            },
+        };
+        date == today
+            && entry
+                .timezone
+                .as_deref()
+                .map_or(true, |name| name == timezone)
+    }
+
+    /// Whether `entry`, cached under `key`, is a verse-of-the-day for a day
+    /// that hasn't come yet.
+    pub fn is_upcoming(&self, key: &CacheKey, entry: &CacheEntry) -> bool {
+        key.is_votd() && entry.date.map_or(false, |date| date > self.today().0)
+    }
+
+    /// Removes verses-of-the-day that are no longer current, and passages
+    /// that haven't been used for `unused_for` (if given), returning how
+    /// many were removed.
+    pub fn prune(&mut self, unused_for: Option<Duration>) -> usize {
+        let now = self.now();
+        let stale: Vec<String> = self
+            .entries()
+            .filter(|(key, entry)| {
+                !(self.is_current(key, entry) || self.is_upcoming(key, entry))
+                    || unused_for.map_or(false, |unused_for| {
+                        now.saturating_sub(entry.used) > unused_for.as_secs()
+                    })
+            })
+            .map(|(key, _)| key.0)
+            .collect();
+        for key in &stale {
+            self.remove(key);
+        }
+        self.dirty |= !stale.is_empty();
+        stale.len()
+    }
+
+    /// Reads the passage cached under `key`, if there is one and it's still
+    /// current; a verse-of-the-day is only current on the day it was cached.
+    /// If the verse-of-the-day for today was fetched ahead of time (under
+    /// [`CacheKey::votd_on`]), it's read instead of an out-of-date one.
+    pub fn get(&mut self, key: &CacheKey) -> Option<CacheEntry> {
+        if key.is_votd() && key.date().is_none() {
+            if let Some(entry) = self.get_current(key) {
+                return Some(entry);
+            }
+            let ahead = CacheKey::votd_on(key.translation(), self.today().0);
+            return self.get_current(&ahead);
+        }
+        self.get_current(key)
+    }
+
+    fn get_current(&mut self, key: &CacheKey) -> Option<CacheEntry> {
+        if !self.is_current(key, self.entries.get(&key.0)?) {
+            return None;
+        }
+        self.touch(key)
+    }
+
+    /// Reads the passage cached under `key` even if it's out of date, e.g.
+    /// to show while a new one is fetched.
+    pub fn get_stale(&mut self, key: &CacheKey) -> Option<CacheEntry> {
+        self.touch(key)
+    }
+
+    /// Notes that the passage cached under `key` was used. Eviction only
+    /// needs a rough order, so the file isn't rewritten for this unless the
+    /// last use was a while ago; a plain cache hit leaves it alone.
+    fn touch(&mut self, key: &CacheKey) -> Option<CacheEntry> {
+        let now = self.now();
+        let entry = self.entries.get_mut(&key.0)?;
+        if now.saturating_sub(entry.used) >= USED_RESOLUTION.as_secs() {
+            entry.used = now;
+            self.changed.insert(key.0.clone());
+            self.dirty = true;
+        }
+        Some(entry.clone())
+    }
+
+    fn remove(&mut self, key: &str) {
+        self.entries.remove(key);
+        self.changed.remove(key);
+        self.removed.insert(key.to_owned());
+    }
+
+    /// Evicts the least recently used passages until there are at most
+    /// `max_entries`.
+    fn evict(&mut self) {
+        while self.entries.len() > self.max_entries {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.used)
+                .map(|(key, _)| key.clone());
+            match oldest {
+                Some(oldest) => self.remove(&oldest),
+                None => break,
+            };
+        }
+    }
+
+    /// Caches `verse` under `key`, evicting the least recently used passages
+    /// if the cache is full.
+    pub fn insert(&mut self, key: &CacheKey, verse: &Verse) {
+        let now = self.now();
+        let (date, timezone) = match key.is_votd() {
+            true => {
+                let (today, timezone) = self.today();
+                (Some(key.date().unwrap_or(today)), Some(timezone))
+            }
+            false => (None, None),
+        };
+        self.entries.insert(
+            key.0.clone(),
+            CacheEntry {
+                verse: verse.clone(),
+                written: now,
+                used: now,
+                date,
+                timezone,
+            },
+        );
+        self.removed.remove(&key.0);
+        self.changed.insert(key.0.clone());
+        self.evict();
+        self.dirty = true;
+    }
+
+    /// Writes any changes back to the cache file. If another process has
+    /// written to it since it was opened, its changes are kept alongside
+    /// these.
+    pub fn save(&mut self) -> Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+        if let CacheHealth::Unsupported { .. } = self.health {
+            // Don't clobber a newer version's cache.
+            return Ok(());
+        }
+        let _lock = lock(&self.path, true)?;
+        let (mut entries, health) = read_entries(&self.path)?;
+        if let CacheHealth::Unsupported { .. } = health {
+            return Ok(());
+        }
+        for key in &self.removed {
+            entries.remove(key);
+        }
+        for key in &self.changed {
+            if let Some(entry) = self.entries.get(key) {
+                match entries.get_mut(key) {
+                    // Another process cached something newer; just note this
+                    // one was used.
+                    Some(theirs) if theirs.written > entry.written => {
+                        theirs.used = theirs.used.max(entry.used)
+                    }
+                    _ => {
+                        entries.insert(key.clone(), entry.clone());
+                    }
+                }
+            }
+        }
+        self.entries = entries;
+        self.evict();
+        write_atomically(&self.path, &encode_entries(&self.entries)?)?;
+        self.changed.clear();
+        self.removed.clear();
+        self.dirty = false;
+        self.health = CacheHealth::Ok;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FixedClock;
+    use chrono::{DateTime, TimeZone, Utc};
+
+    fn verse(text: &str) -> Verse {
+        Verse {
+            verses: vec![VerseText {
+                book: "John".to_owned(),
+                chapter: 3,
+                verse: 16,
+                text: text.to_owned(),
+                styles: Vec::new(),
+                headings: Vec::new(),
+            }],
+        }
+    }
+
+    fn utc(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    fn open_at(path: &Path, now: DateTime<Utc>) -> VerseCache {
+        VerseCache::open(path, VerseCache::DEFAULT_MAX_ENTRIES)
+            .unwrap()
+            .with_clock(FixedClock(now))
+    }
+
+    fn passage_key() -> CacheKey {
+        CacheKey::passage("NET", &"John 3:16".parse().unwrap())
+    }
+
+    #[test]
+    fn cache_hits_dont_rewrite_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache");
+        let written = utc(2026, 10, 18, 12, 0);
+        let mut cache = open_at(&path, written);
+        cache.insert(&passage_key(), &verse("For God so loved the world"));
+        cache.save().unwrap();
+        let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+
+        // Soon after, a hit leaves the file as it is.
+        let mut cache = open_at(&path, utc(2026, 10, 18, 12, 30));
+        assert!(cache.get(&passage_key()).is_some());
+        assert!(!cache.dirty);
+        cache.save().unwrap();
+        assert_eq!(
+            std::fs::metadata(&path).unwrap().modified().unwrap(),
+            modified
+        );
+        assert_eq!(
+            cache.entries[&passage_key().0].used,
+            written.timestamp() as u64
+        );
+
+        // Much later, the use is recorded so eviction sees it.
+        let later = utc(2026, 10, 18, 14, 0);
+        let mut cache = open_at(&path, later);
+        assert!(cache.get(&passage_key()).is_some());
+        assert!(cache.dirty);
+        cache.save().unwrap();
+        let cache = open_at(&path, later);
+        assert_eq!(
+            cache.entries[&passage_key().0].used,
+            later.timestamp() as u64
+        );
+    }
+
+    /// 23:50 on October 18th in Chicago (CDT, UTC-5), the provider's
+    /// timezone, which is already October 19th in UTC and London.
+    fn before_midnight() -> DateTime<Utc> {
+        utc(2026, 10, 19, 4, 50)
+    }
+
+    fn in_timezone(path: &Path, now: DateTime<Utc>, timezone: Timezone) -> VerseCache {
+        open_at(path, now).with_timezone(timezone, chrono_tz::America::Chicago)
+    }
+
+    #[test]
+    fn votd_is_current_until_midnight_in_the_provider_timezone() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache");
+        let key = CacheKey::votd("NET");
+        let mut cache = in_timezone(&path, before_midnight(), Timezone::Provider);
+        cache.insert(&key, &verse("Today's"));
+        cache.save().unwrap();
+        let entry = cache.entries[&key.0].clone();
+        assert_eq!(entry.date, NaiveDate::from_ymd_opt(2026, 10, 18));
+        assert_eq!(entry.timezone.as_deref(), Some("America/Chicago"));
+
+        let mut cache = in_timezone(&path, utc(2026, 10, 19, 4, 59), Timezone::Provider);
+        assert!(cache.is_current(&key, &entry));
+        assert!(cache.get(&key).is_some());
+
+        let mut cache = in_timezone(&path, utc(2026, 10, 19, 5, 1), Timezone::Provider);
+        assert!(!cache.is_current(&key, &entry));
+        assert!(cache.get(&key).is_none());
+        assert!(cache.get_stale(&key).is_some());
+    }
+
+    #[test]
+    fn votd_is_current_until_midnight_in_a_named_timezone() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache");
+        let key = CacheKey::votd("NET");
+        let london = Timezone::Named(chrono_tz::Europe::London);
+        // 23:50 on October 18th in London (BST, UTC+1).
+        let mut cache = in_timezone(&path, utc(2026, 10, 18, 22, 50), london);
+        cache.insert(&key, &verse("Today's"));
+        cache.save().unwrap();
+        let entry = cache.entries[&key.0].clone();
+        assert_eq!(entry.timezone.as_deref(), Some("Europe/London"));
+
+        // Past midnight in London, though it's still the 18th in Chicago.
+        let cache = in_timezone(&path, utc(2026, 10, 18, 23, 1), london);
+        assert!(!cache.is_current(&key, &entry));
+        // An entry cached for another timezone isn't current in this one,
+        // even on the same date.
+        let cache = in_timezone(&path, utc(2026, 10, 18, 22, 55), Timezone::Provider);
+        assert!(!cache.is_current(&key, &entry));
+        let cache = in_timezone(&path, utc(2026, 10, 18, 22, 55), london);
+        assert!(cache.is_current(&key, &entry));
+    }
+
+    #[test]
+    fn votd_is_current_until_midnight_in_the_local_timezone() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache");
+        let key = CacheKey::votd("NET");
+        let written = before_midnight();
+        let mut cache = in_timezone(&path, written, Timezone::Local);
+        cache.insert(&key, &verse("Today's"));
+        let entry = cache.entries[&key.0].clone();
+        assert_eq!(entry.timezone.as_deref(), Some("local"));
+
+        // Whatever the system's timezone is, the entry is current exactly
+        // while it's the same day there.
+        let local_date = |now: DateTime<Utc>| now.with_timezone(&chrono::Local).date_naive();
+        for minutes in [1, 30, 60 * 12, 60 * 25] {
+            let now = written + chrono::Duration::minutes(minutes);
+            let cache = in_timezone(&path, now, Timezone::Local);
+            assert_eq!(
+                cache.is_current(&key, &entry),
+                local_date(now) == local_date(written),
+                "{} minutes later",
+                minutes
+            );
+        }
+    }
+
+    #[test]
+    fn legacy_votd_entries_are_dated_by_when_they_were_written() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache");
+        let key = CacheKey::votd("NET");
+        let legacy = CacheEntry {
+            verse: verse("Cached before entries were dated"),
+            written: before_midnight().timestamp() as u64,
+            used: before_midnight().timestamp() as u64,
+            date: None,
+            timezone: None,
+        };
+        let mut cache = in_timezone(&path, utc(2026, 10, 19, 4, 59), Timezone::Provider);
+        cache.entries.insert(key.0.clone(), legacy.clone());
+        assert!(cache.is_current(&key, &legacy));
+        assert!(cache.get(&key).is_some());
+
+        let cache = in_timezone(&path, utc(2026, 10, 19, 5, 1), Timezone::Provider);
+        assert!(!cache.is_current(&key, &legacy));
+        // In London, it was written on the 19th, so it's current all day.
+        let london = Timezone::Named(chrono_tz::Europe::London);
+        let cache = in_timezone(&path, utc(2026, 10, 19, 22, 0), london);
+        assert!(cache.is_current(&key, &legacy));
+    }
+
+    #[test]
+    fn warmed_votd_is_read_on_its_day() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache");
+        let tomorrow = NaiveDate::from_ymd_opt(2026, 10, 19).unwrap();
+        let mut cache = in_timezone(&path, before_midnight(), Timezone::Provider);
+        cache.insert(&CacheKey::votd("NET"), &verse("Today's"));
+        cache.insert(&CacheKey::votd_on("NET", tomorrow), &verse("Tomorrow's"));
+        cache.save().unwrap();
+
+        let mut cache = in_timezone(&path, utc(2026, 10, 19, 5, 1), Timezone::Provider);
+        let entry = cache.get(&CacheKey::votd("NET")).unwrap();
+        assert_eq!(entry.verse.text(), "Tomorrow's");
+    }
+
+    #[test]
+    fn prune_keeps_current_and_upcoming_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache");
+        let today = NaiveDate::from_ymd_opt(2026, 10, 18).unwrap();
+        let mut cache = in_timezone(&path, before_midnight(), Timezone::Provider);
+        cache.insert(&CacheKey::votd("NET"), &verse("Today's"));
+        cache.insert(&CacheKey::votd_on("NET", today), &verse("Today's"));
+        cache.insert(
+            &CacheKey::votd_on("NET", today.succ_opt().unwrap()),
+            &verse("Tomorrow's"),
+        );
+        cache.insert(&passage_key(), &verse("For God so loved the world"));
+        cache.save().unwrap();
+
+        // Just before midnight, nothing is out of date.
+        let mut cache = in_timezone(&path, utc(2026, 10, 19, 4, 59), Timezone::Provider);
+        assert_eq!(cache.prune(None), 0);
+
+        // Just after, today's verses are.
+        let mut cache = in_timezone(&path, utc(2026, 10, 19, 5, 1), Timezone::Provider);
+        assert_eq!(cache.prune(None), 2);
+        let mut keys: Vec<String> = cache.entries().map(|(key, _)| key.0).collect();
+        keys.sort();
+        assert_eq!(keys, ["passage/NET/John 3:16", "votd/NET/2026-10-19"]);
+        cache.save().unwrap();
+
+        // Passages go once they haven't been used for long enough.
+        let mut cache = in_timezone(&path, utc(2026, 10, 30, 0, 0), Timezone::Provider);
+        assert_eq!(cache.prune(Some(Duration::from_secs(7 * 24 * 60 * 60))), 2);
+        assert!(cache.is_empty());
+    }
+
+    /// When the fixtures are taken to have been written.
+    const MODIFIED: u64 = 1_792_300_600;
+
+    fn fixture_verse(verse: u32, text: &str) -> Verse {
+        Verse {
+            verses: vec![VerseText {
+                book: "John".to_owned(),
+                chapter: 3,
+                verse,
+                text: text.to_owned(),
+                styles: Vec::new(),
+                headings: Vec::new(),
+            }],
+        }
+    }
+
+    /// The two entries in the headerless and version 2 fixtures.
+    fn fixture_entries() -> BTreeMap<String, CacheEntry> {
+        BTreeMap::from_iter([
+            (
+                "passage/NET/John 3:17".to_owned(),
+                CacheEntry {
+                    verse: fixture_verse(17, "For God did not send his Son"),
+                    written: 1_792_200_000,
+                    used: 1_792_250_000,
+                    date: None,
+                    timezone: None,
+                },
+            ),
+            (
+                "votd/NET".to_owned(),
+                CacheEntry {
+                    verse: fixture_verse(16, "For God so loved the world"),
+                    written: 1_792_300_000,
+                    used: 1_792_300_500,
+                    date: None,
+                    timezone: None,
+                },
+            ),
+        ])
+    }
+
+    /// What a single verse-of-the-day migrates to.
+    fn migrated_votd() -> BTreeMap<String, CacheEntry> {
+        BTreeMap::from_iter([(
+            "votd/NET".to_owned(),
+            CacheEntry {
+                verse: fixture_verse(16, "For God so loved the world"),
+                written: MODIFIED,
+                used: MODIFIED,
+                date: None,
+                timezone: None,
+            },
+        )])
+    }
+
+    #[test]
+    fn migrates_the_original_title_and_text() {
+        let bytes = include_bytes!("../tests/fixtures/cache/legacy-title-text.bin");
+        assert_eq!(
+            decode_entries(bytes, MODIFIED),
+            (migrated_votd(), CacheHealth::Migrated { from: 1 })
+        );
+    }
+
+    #[test]
+    fn migrates_a_per_verse_votd() {
+        let bytes = include_bytes!("../tests/fixtures/cache/per-verse.bin");
+        assert_eq!(
+            decode_entries(bytes, MODIFIED),
+            (migrated_votd(), CacheHealth::Migrated { from: 1 })
+        );
+    }
+
+    #[test]
+    fn migrates_entries_without_a_header() {
+        let bytes = include_bytes!("../tests/fixtures/cache/headerless.bin");
+        assert_eq!(
+            decode_entries(bytes, MODIFIED),
+            (fixture_entries(), CacheHealth::Migrated { from: 1 })
+        );
+    }
+
+    #[test]
+    fn salvages_entries_with_a_bad_checksum() {
+        let bytes = include_bytes!("../tests/fixtures/cache/bad-crc.bin");
+        assert_eq!(
+            decode_entries(bytes, MODIFIED),
+            (
+                fixture_entries(),
+                CacheHealth::Damaged {
+                    kept: 2,
+                    dropped: 0
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn leaves_a_newer_version_alone() {
+        let bytes = include_bytes!("../tests/fixtures/cache/future-version.bin");
+        assert_eq!(
+            decode_entries(bytes, MODIFIED),
+            (BTreeMap::new(), CacheHealth::Unsupported { version: 3 })
+        );
+    }
+
+    #[test]
+    fn truncated_files_are_unreadable() {
+        let bytes = include_bytes!("../tests/fixtures/cache/truncated.bin");
+        assert_eq!(
+            decode_entries(bytes, MODIFIED),
+            (BTreeMap::new(), CacheHealth::Unreadable)
+        );
+        // Cut off within the header, too.
+        assert_eq!(
+            decode_entries(&bytes[..HEADER_LEN - 1], MODIFIED),
+            (BTreeMap::new(), CacheHealth::Unreadable)
+        );
+    }
+}
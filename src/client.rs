@@ -0,0 +1,233 @@
+use crate::error::Result;
+use crate::provider::{NetBibleProvider, VerseProvider};
+use crate::reference::Reference;
+use crate::store::BibleStore;
+use crate::verse::Verse;
+use crate::versification::Canon;
+use chrono::NaiveDate;
+use std::time::Duration;
+
+/// A client for looking up verses from a [`VerseProvider`], optionally
+/// answering lookups from a [`BibleStore`] first.
+pub struct VerseClient {
+    provider: Box<dyn VerseProvider>,
+    store: Option<BibleStore>,
+    canon: Canon,
+}
+
+impl VerseClient {
+    /// Creates a client using the NET Bible API, whose requests give up after
+    /// `timeout`.
+    pub fn new(timeout: Duration) -> Result<Self> {
+        Ok(VerseClient::with_provider(NetBibleProvider::new(timeout)?))
+    }
+
+    /// Creates a client that looks verses up from `provider`.
+    pub fn with_provider<P: VerseProvider + 'static>(provider: P) -> Self {
+        VerseClient::from_boxed(Box::new(provider))
+    }
+
+    /// Creates a client from an already boxed provider, such as one returned
+    /// by [`provider::by_name`](crate::provider::by_name).
+    pub fn from_boxed(provider: Box<dyn VerseProvider>) -> Self {
+        VerseClient {
+            provider,
+            store: None,
+            canon: Canon::default(),
+        }
+    }
+
+    /// Answers lookups from `store` when it holds the requested passage,
+    /// falling back to the provider otherwise.
+    pub fn with_store(mut self, store: BibleStore) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Checks references against `canon` instead of the Protestant canon.
+    pub fn with_canon(mut self, canon: Canon) -> Self {
+        self.canon = canon;
+        self
+    }
+
+    /// The canon references are checked against.
+    pub fn canon(&self) -> Canon {
+        self.canon
+    }
+
+    /// The provider this client looks verses up from.
+    pub fn provider(&self) -> &dyn VerseProvider {
+        self.provider.as_ref()
+    }
+
+    /// Retrieves the current verse-of-the-day.
+    pub fn votd(&self) -> Result<Verse> {
+        self.provider.votd()
+    }
+
+    /// Retrieves the verse-of-the-day for `date`, if the provider can look
+    /// up days other than today.
+    pub fn votd_on(&self, date: NaiveDate) -> Result<Option<Verse>> {
+        self.provider.votd_on(date)
+    }
+
+    /// Retrieves a random verse.
+    pub fn random(&self) -> Result<Verse> {
+        self.provider.random()
+    }
+
+    /// Looks up a passage, such as "John 3:16" or "Gen 1:1-3". The reference
+    /// is parsed (see [`Reference`]) before anything is requested, so invalid
+    /// references are reported without a network request.
+    pub fn lookup(&self, passage: &str) -> Result<Verse> {
+        self.lookup_reference(&Reference::parse_with(passage, self.canon)?)
+    }
+
+    /// Looks up the passages in an already parsed reference.
+    pub fn lookup_reference(&self, reference: &Reference) -> Result<Verse> {
+        self.lookup_translated(reference).map(|(verse, _)| verse)
+    }
+
+    /// Looks up the passages in an already parsed reference, along with the
+    /// translation of whichever of the store or the provider answered.
+    pub fn lookup_translated(&self, reference: &Reference) -> Result<(Verse, &str)> {
+        if let Some(found) = self.lookup_stored(reference)? {
+            return Ok(found);
+        }
+        Ok((
+            self.provider.lookup(reference)?,
+            self.provider.translation(),
+        ))
+    }
+
+    /// Looks up the passages in an already parsed reference from the store
+    /// alone, along with its translation; `None` if there's no store or it
+    /// doesn't hold them all.
+    pub fn lookup_stored(&self, reference: &Reference) -> Result<Option<(Verse, &str)>> {
+        match &self.store {
+            Some(store) => Ok(store
+                .lookup(reference)?
+                .map(|verse| (verse, store.translation()))),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::VotdError;
+    use crate::store::ImportFormat;
+    use crate::verse::VerseText;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// A provider that answers every lookup with the same verse, counting
+    /// how many times it's asked.
+    struct MockProvider {
+        lookups: Arc<AtomicUsize>,
+    }
+
+    fn mock_verse() -> Verse {
+        Verse {
+            verses: vec![VerseText {
+                book: "Mock".to_owned(),
+                chapter: 1,
+                verse: 1,
+                text: "From the provider.".to_owned(),
+                styles: Vec::new(),
+                headings: Vec::new(),
+            }],
+        }
+    }
+
+    impl VerseProvider for MockProvider {
+        fn name(&self) -> &str {
+            "mock"
+        }
+
+        fn translation(&self) -> &str {
+            "MOCK"
+        }
+
+        fn lookup(&self, _reference: &Reference) -> Result<Verse> {
+            self.lookups.fetch_add(1, Ordering::SeqCst);
+            Ok(mock_verse())
+        }
+
+        fn votd(&self) -> Result<Verse> {
+            Ok(mock_verse())
+        }
+
+        fn random(&self) -> Result<Verse> {
+            Ok(mock_verse())
+        }
+    }
+
+    fn mock_client() -> (VerseClient, Arc<AtomicUsize>) {
+        let lookups = Arc::new(AtomicUsize::new(0));
+        let client = VerseClient::with_provider(MockProvider {
+            lookups: Arc::clone(&lookups),
+        });
+        (client, lookups)
+    }
+
+    #[test]
+    fn invalid_references_fail_before_the_provider_is_called() {
+        let (client, lookups) = mock_client();
+        for passage in ["", "Hezekiah 1:1", "John 30:1", "John 3:99"] {
+            match client.lookup(passage) {
+                Err(VotdError::InvalidReference(_)) => {}
+                other => panic!("{:?} gave {:?}", passage, other),
+            }
+        }
+        assert_eq!(lookups.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn valid_references_go_to_the_provider() {
+        let (client, lookups) = mock_client();
+        let verse = client.lookup("John 3:16").unwrap();
+        assert_eq!(verse.text(), "From the provider.");
+        assert_eq!(lookups.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn store_hits_skip_the_provider() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("john.json");
+        std::fs::write(
+            &source,
+            r#"[
+                {"book": "John", "chapter": 3, "verse": 16, "text": "From the store."},
+                {"book": "John", "chapter": 3, "verse": 17, "text": "Also stored."}
+            ]"#,
+        )
+        .unwrap();
+        let root = dir.path().join("bibles");
+        BibleStore::import(&root, "TEST", &source, ImportFormat::Json).unwrap();
+        let store = BibleStore::open(&root, "TEST").unwrap().unwrap();
+        let (client, lookups) = mock_client();
+        let client = client.with_store(store);
+
+        let (verse, translation) = client
+            .lookup_translated(&"John 3:16-17".parse().unwrap())
+            .unwrap();
+        assert_eq!(verse.text(), "From the store. Also stored.");
+        assert_eq!(translation, "TEST");
+        assert_eq!(lookups.load(Ordering::SeqCst), 0);
+
+        // Passages the store doesn't have still go to the provider, and are
+        // in its translation.
+        let (verse, translation) = client
+            .lookup_translated(&"John 3:18".parse().unwrap())
+            .unwrap();
+        assert_eq!(verse.text(), "From the provider.");
+        assert_eq!(translation, "MOCK");
+        assert_eq!(lookups.load(Ordering::SeqCst), 1);
+        assert!(client
+            .lookup_stored(&"John 3:18".parse().unwrap())
+            .unwrap()
+            .is_none());
+    }
+}
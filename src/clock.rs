@@ -0,0 +1,91 @@
+//! The current time, and which day it is, for deciding whether a cached
+//! verse-of-the-day is still today's.
+
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use chrono_tz::Tz;
+use std::fmt;
+use std::str::FromStr;
+
+/// A source of the current time, so it can be fixed (e.g. in tests).
+pub trait Clock: fmt::Debug + Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// The system clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A clock that's always at the same time.
+#[derive(Debug, Clone, Copy)]
+pub struct FixedClock(pub DateTime<Utc>);
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.0
+    }
+}
+
+/// The timezone whose midnight starts a new verse-of-the-day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Timezone {
+    /// The provider's timezone, when its verse-of-the-day actually changes.
+    #[default]
+    Provider,
+    /// The system's local timezone.
+    Local,
+    /// A named timezone, e.g. "Europe/London".
+    Named(Tz),
+}
+
+impl Timezone {
+    /// The date at `now` in this timezone, given the provider's timezone.
+    pub fn date(&self, provider: Tz, now: DateTime<Utc>) -> NaiveDate {
+        match self {
+            Timezone::Provider => now.with_timezone(&provider).date_naive(),
+            Timezone::Local => now.with_timezone(&Local).date_naive(),
+            Timezone::Named(tz) => now.with_timezone(tz).date_naive(),
+        }
+    }
+
+    /// The name of the timezone, resolving `Provider` to the provider's.
+    pub fn name(&self, provider: Tz) -> String {
+        match self {
+            Timezone::Provider => provider.name().to_owned(),
+            Timezone::Local => "local".to_owned(),
+            Timezone::Named(tz) => tz.name().to_owned(),
+        }
+    }
+}
+
+impl fmt::Display for Timezone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Timezone::Provider => f.write_str("provider"),
+            Timezone::Local => f.write_str("local"),
+            Timezone::Named(tz) => f.write_str(tz.name()),
+        }
+    }
+}
+
+impl FromStr for Timezone {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "provider" => Ok(Timezone::Provider),
+            "local" => Ok(Timezone::Local),
+            _ => s.parse().map(Timezone::Named).map_err(|_| {
+                format!(
+                    "unknown timezone {:?}; expected provider, local, or a name like Europe/London",
+                    s
+                )
+            }),
+        }
+    }
+}
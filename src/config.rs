@@ -0,0 +1,498 @@
+//! The configuration file, which holds defaults for the command-line
+//! options, e.g. `~/.config/votd/config.toml`. Settings can also be given as
+//! `VOTD_<SETTING>` environment variables (e.g. `VOTD_TIMEOUT=5`), which take
+//! precedence over the file, and overridden for a single run with `--set
+//! <setting>=<value>`, which takes precedence over both.
+
+use crate::cache::Fallback;
+use crate::clock::Timezone;
+use crate::error::{Result, VotdError};
+use crate::network::Tls;
+use crate::output::OutputFormat;
+use crate::render::NumberStyle;
+use crate::template::Template;
+use crate::versification::Canon;
+use serde_derive::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Settings from the configuration file and environment. Every setting is
+/// optional; unset ones fall back to the built-in defaults.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Don't read from or write to the cache.
+    pub no_cache: Option<bool>,
+    /// The most passages to keep in the cache.
+    pub cache_entries: Option<usize>,
+    /// Show an out-of-date cached verse-of-the-day straight away, and fetch
+    /// the new one in the background for next time.
+    pub background_refresh: Option<bool>,
+    /// When to show an out-of-date cached passage if a current one can't be
+    /// fetched.
+    #[serde(with = "named")]
+    pub fallback: Option<Fallback>,
+    /// Only show the text of passages, without their titles.
+    pub only_verse: Option<bool>,
+    /// Show the translation after the title.
+    pub show_translation: Option<bool>,
+    /// How long a request may take in total, in seconds.
+    pub timeout: Option<u64>,
+    /// How long to wait to connect to the provider, in seconds; defaults to
+    /// the request timeout.
+    pub connect_timeout: Option<u64>,
+    /// How many times to retry a request that failed because the provider
+    /// couldn't be reached or had a problem.
+    pub retries: Option<u32>,
+    /// How long to wait before the first retry, in milliseconds; each one
+    /// after waits about twice as long.
+    pub retry_delay_ms: Option<u64>,
+    /// The proxy to send requests through, or "none"; by default, the
+    /// `HTTPS_PROXY` environment variable and the like are used.
+    pub proxy: Option<String>,
+    /// A PEM file of extra root certificates to trust.
+    pub ca_bundle: Option<PathBuf>,
+    /// The TLS implementation to use: native or rustls.
+    #[serde(with = "named")]
+    pub tls: Option<Tls>,
+    /// The provider to look verses up from (see [`PROVIDER_NAMES`]).
+    ///
+    /// [`PROVIDER_NAMES`]: crate::provider::PROVIDER_NAMES
+    pub provider: Option<String>,
+    /// The imported translation to read verses from offline; defaults to
+    /// the provider's translation.
+    pub translation: Option<String>,
+    /// The canon to check references against.
+    #[serde(with = "named")]
+    pub canon: Option<Canon>,
+    /// Don't wrap text to the terminal width.
+    pub no_wrap: Option<bool>,
+    /// Show verse numbers inline with the text.
+    pub verse_numbers: Option<bool>,
+    /// How to write inline verse numbers.
+    #[serde(with = "named")]
+    pub number_style: Option<NumberStyle>,
+    /// Start each verse on its own line.
+    pub one_per_line: Option<bool>,
+    /// Show emphasized text using terminal styles.
+    pub emphasis: Option<bool>,
+    /// Show section headings above the verses they start.
+    pub headings: Option<bool>,
+    /// The most verses to show on each page of a chapter or book; by
+    /// default, each chapter is a page.
+    pub limit: Option<usize>,
+    /// Print long output straight to the terminal instead of through
+    /// `$PAGER`.
+    pub no_pager: Option<bool>,
+    /// The timezone whose midnight starts a new verse-of-the-day.
+    #[serde(with = "named")]
+    pub timezone: Option<Timezone>,
+    /// Write passages in this format instead of laying them out for the
+    /// terminal.
+    #[serde(with = "named")]
+    pub format: Option<OutputFormat>,
+    /// The template to lay out passages with (see [`Template`]).
+    pub template: Option<String>,
+}
+
+/// (De)serializes optional settings using their `FromStr` and `Display`
+/// implementations, so they're written by name in the file.
+mod named {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::fmt::Display;
+    use std::str::FromStr;
+
+    pub fn serialize<T: Display, S: Serializer>(
+        value: &Option<T>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => serializer.serialize_str(&value.to_string()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+    where
+        T: FromStr<Err = String>,
+        D: Deserializer<'de>,
+    {
+        Option::<String>::deserialize(deserializer)?
+            .map(|name| name.parse().map_err(serde::de::Error::custom))
+            .transpose()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Switch,
+    /// A whole number no larger than the given maximum, which is that of
+    /// the setting's type.
+    Number(u64),
+    Text,
+}
+
+/// Every setting, with the kind of value it takes and its default.
+const SETTINGS: &[(&str, Kind, Option<&str>)] = &[
+    ("no_cache", Kind::Switch, Some("false")),
+    ("cache_entries", Kind::Number(usize::MAX as u64), Some("64")),
+    ("background_refresh", Kind::Switch, Some("false")),
+    ("fallback", Kind::Text, Some("on-network-error")),
+    ("only_verse", Kind::Switch, Some("false")),
+    ("show_translation", Kind::Switch, Some("false")),
+    ("timeout", Kind::Number(u64::MAX), Some("2")),
+    ("connect_timeout", Kind::Number(u64::MAX), None),
+    ("retries", Kind::Number(u32::MAX as u64), Some("0")),
+    ("retry_delay_ms", Kind::Number(u64::MAX), Some("250")),
+    ("proxy", Kind::Text, None),
+    ("ca_bundle", Kind::Text, None),
+    #[cfg(feature = "native-tls")]
+    ("tls", Kind::Text, Some("native")),
+    #[cfg(not(feature = "native-tls"))]
+    ("tls", Kind::Text, Some("rustls")),
+    ("provider", Kind::Text, Some("net")),
+    ("translation", Kind::Text, None),
+    ("canon", Kind::Text, Some("protestant")),
+    ("no_wrap", Kind::Switch, Some("false")),
+    ("verse_numbers", Kind::Switch, Some("on for whole chapters")),
+    ("number_style", Kind::Text, Some("superscript")),
+    ("one_per_line", Kind::Switch, Some("false")),
+    ("emphasis", Kind::Switch, Some("false")),
+    ("headings", Kind::Switch, Some("false")),
+    ("limit", Kind::Number(usize::MAX as u64), None),
+    ("no_pager", Kind::Switch, Some("false")),
+    ("timezone", Kind::Text, Some("provider")),
+    ("format", Kind::Text, None),
+    ("template", Kind::Text, None),
+];
+
+/// The names of every setting.
+pub fn setting_names() -> impl Iterator<Item = &'static str> {
+    SETTINGS.iter().map(|(name, _, _)| *name)
+}
+
+/// Where a setting's effective value comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Default,
+    File,
+    Environment,
+    /// Given with `--set` on the command line.
+    CommandLine,
+}
+
+/// A setting's effective value, as reported by [`Config::describe`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Setting {
+    pub name: &'static str,
+    /// The value, formatted as it would be in the file, if it's set.
+    pub value: Option<String>,
+    pub source: Source,
+}
+
+fn config_error(path: &Path, message: impl std::fmt::Display) -> VotdError {
+    VotdError::Config(format!("{}: {}", path.display(), message))
+}
+
+fn kind_of(name: &str) -> std::result::Result<Kind, String> {
+    SETTINGS
+        .iter()
+        .find(|(setting, _, _)| *setting == name)
+        .map(|(_, kind, _)| *kind)
+        .ok_or_else(|| {
+            format!(
+                "unknown setting {:?}; expected one of: {}",
+                name,
+                setting_names().collect::<Vec<_>>().join(", ")
+            )
+        })
+}
+
+/// Converts the text of a setting (e.g. from the environment) to a value.
+fn parse_value(name: &str, value: &str) -> std::result::Result<toml::Value, String> {
+    let invalid = |expected: &str| {
+        format!(
+            "invalid value {:?} for {}; expected {}",
+            value, name, expected
+        )
+    };
+    Ok(match kind_of(name)? {
+        Kind::Switch => match value.trim().to_ascii_lowercase().as_str() {
+            "1" | "true" | "yes" | "on" => toml::Value::Boolean(true),
+            "0" | "false" | "no" | "off" | "" => toml::Value::Boolean(false),
+            _ => return Err(invalid("true or false")),
+        },
+        Kind::Number(max) => {
+            // TOML integers are signed, so nothing larger fits in the file.
+            let max = max.min(i64::MAX as u64);
+            match value.trim().parse::<u64>() {
+                Ok(n) if n <= max => toml::Value::Integer(n as i64),
+                _ => return Err(invalid(&format!("a whole number up to {}", max))),
+            }
+        }
+        Kind::Text => toml::Value::String(value.to_owned()),
+    })
+}
+
+fn format_value(value: &toml::Value) -> String {
+    match value {
+        toml::Value::String(s) => s.clone(),
+        value => value.to_string(),
+    }
+}
+
+impl Config {
+    /// The default location of the configuration file, if one can be
+    /// determined.
+    pub fn default_path() -> Option<PathBuf> {
+        directories::BaseDirs::new().map(|dirs| dirs.config_dir().join("votd").join("config.toml"))
+    }
+
+    fn read_table(path: &Path) -> Result<toml::Table> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(toml::Table::new()),
+            Err(e) => return Err(config_error(path, e)),
+        };
+        let table: toml::Table = contents.parse().map_err(|e| config_error(path, e))?;
+        // Check the settings are valid, so errors mention the file.
+        Config::from_table(table.clone()).map_err(|e| config_error(path, e))?;
+        Ok(table)
+    }
+
+    fn env_table() -> Result<toml::Table> {
+        let mut table = toml::Table::new();
+        for name in setting_names() {
+            let var = format!("VOTD_{}", name.to_ascii_uppercase());
+            if let Ok(value) = std::env::var(&var) {
+                let value = parse_value(name, &value).and_then(|value| {
+                    // Check the value is valid, so errors mention the variable.
+                    let single = toml::Table::from_iter([(name.to_owned(), value.clone())]);
+                    Config::from_table(single).map(|_| value)
+                });
+                let value = value.map_err(|e| VotdError::Config(format!("{}: {}", var, e)))?;
+                table.insert(name.to_owned(), value);
+            }
+        }
+        Ok(table)
+    }
+
+    /// Parses overrides written as `name=value`, e.g. from `--set`.
+    fn override_table(overrides: &[String]) -> Result<toml::Table> {
+        let mut table = toml::Table::new();
+        for setting in overrides {
+            let invalid = |e: String| VotdError::Config(format!("--set {}: {}", setting, e));
+            let (name, value) = setting
+                .split_once('=')
+                .ok_or_else(|| invalid("expected <setting>=<value>".to_owned()))?;
+            let name = name.trim().replace('-', "_");
+            let value = parse_value(&name, value).and_then(|value| {
+                // Check the value is valid, so errors mention the override.
+                let single = toml::Table::from_iter([(name.clone(), value.clone())]);
+                Config::from_table(single).map(|_| value)
+            });
+            table.insert(name, value.map_err(invalid)?);
+        }
+        Ok(table)
+    }
+
+    fn from_table(table: toml::Table) -> std::result::Result<Self, String> {
+        let config: Config = toml::Value::Table(table)
+            .try_into()
+            .map_err(|e: toml::de::Error| e.message().to_owned())?;
+        if let Some(template) = &config.template {
+            template
+                .parse::<Template>()
+                .map_err(|e| format!("invalid template: {}", e))?;
+        }
+        Ok(config)
+    }
+
+    /// Reads the configuration file at `path`; a missing file is the same
+    /// as an empty one.
+    pub fn load(path: &Path) -> Result<Self> {
+        Config::from_table(Config::read_table(path)?).map_err(|e| config_error(path, e))
+    }
+
+    /// Reads the configuration file at `path` (if any), with environment
+    /// variables taking precedence over it.
+    pub fn load_with_env(path: Option<&Path>) -> Result<Self> {
+        Config::load_with_overrides(path, &[])
+    }
+
+    /// Reads the configuration file at `path` (if any), with environment
+    /// variables taking precedence over it, and `overrides` (written as
+    /// `name=value`) over both.
+    pub fn load_with_overrides(path: Option<&Path>, overrides: &[String]) -> Result<Self> {
+        let mut table = match path {
+            Some(path) => Config::read_table(path)?,
+            None => toml::Table::new(),
+        };
+        table.extend(Config::env_table()?);
+        table.extend(Config::override_table(overrides)?);
+        Config::from_table(table).map_err(VotdError::Config)
+    }
+
+    /// The effective value of every setting (ignoring command-line flags
+    /// other than `overrides`), and where it comes from.
+    pub fn describe(path: Option<&Path>, overrides: &[String]) -> Result<Vec<Setting>> {
+        let file = match path {
+            Some(path) => Config::read_table(path)?,
+            None => toml::Table::new(),
+        };
+        let env = Config::env_table()?;
+        let overrides = Config::override_table(overrides)?;
+        Ok(SETTINGS
+            .iter()
+            .map(|&(name, _, default)| {
+                let (value, source) = if let Some(value) = overrides.get(name) {
+                    (Some(format_value(value)), Source::CommandLine)
+                } else if let Some(value) = env.get(name) {
+                    (Some(format_value(value)), Source::Environment)
+                } else if let Some(value) = file.get(name) {
+                    (Some(format_value(value)), Source::File)
+                } else {
+                    (default.map(str::to_owned), Source::Default)
+                };
+                Setting {
+                    name,
+                    value,
+                    source,
+                }
+            })
+            .collect())
+    }
+
+    /// The value of `name` in the configuration file at `path`, if it's
+    /// set there.
+    pub fn get(path: &Path, name: &str) -> Result<Option<String>> {
+        kind_of(name).map_err(VotdError::Config)?;
+        Ok(Config::read_table(path)?.get(name).map(format_value))
+    }
+
+    /// Sets `name` to `value` in the configuration file at `path`, creating
+    /// the file if needed. Other settings and comments in the file are
+    /// kept as they are.
+    pub fn set(path: &Path, name: &str, value: &str) -> Result<()> {
+        let value = match parse_value(name, value).map_err(VotdError::Config)? {
+            toml::Value::Boolean(b) => toml_edit::Value::from(b),
+            toml::Value::Integer(n) => toml_edit::Value::from(n),
+            value => toml_edit::Value::from(format_value(&value)),
+        };
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(config_error(path, e)),
+        };
+        let mut document: toml_edit::DocumentMut =
+            contents.parse().map_err(|e| config_error(path, e))?;
+        document[name] = toml_edit::value(value);
+        let contents = document.to_string();
+        let table: toml::Table = contents.parse().map_err(|e| config_error(path, e))?;
+        Config::from_table(table).map_err(|e| config_error(path, e))?;
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| config_error(path, e))?;
+        }
+        std::fs::write(path, contents).map_err(|e| config_error(path, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_config(contents: &str) -> (tempfile::TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, contents).unwrap();
+        (dir, path)
+    }
+
+    fn overrides(settings: &[&str]) -> Vec<String> {
+        settings.iter().map(|s| s.to_string()).collect()
+    }
+
+    /// Environment variables are process-wide, so every check that sets one
+    /// is in this one test.
+    #[test]
+    fn overrides_take_precedence_over_the_environment_and_file() {
+        let (_dir, path) = write_config("timeout = 5\nno_wrap = true\nemphasis = true\n");
+
+        std::env::remove_var("VOTD_TIMEOUT");
+        let config = Config::load_with_overrides(Some(&path), &[]).unwrap();
+        assert_eq!(config.timeout, Some(5));
+
+        std::env::set_var("VOTD_TIMEOUT", "7");
+        let config = Config::load_with_overrides(Some(&path), &[]).unwrap();
+        assert_eq!(config.timeout, Some(7));
+
+        let config =
+            Config::load_with_overrides(Some(&path), &overrides(&["timeout=9", "no-wrap=false"]))
+                .unwrap();
+        assert_eq!(config.timeout, Some(9));
+        assert_eq!(config.no_wrap, Some(false));
+        assert_eq!(config.emphasis, Some(true));
+
+        let described = Config::describe(Some(&path), &overrides(&["no_wrap=off"])).unwrap();
+        let source = |name: &str| {
+            let setting = described.iter().find(|s| s.name == name).unwrap();
+            (setting.value.clone(), setting.source)
+        };
+        assert_eq!(
+            source("timeout"),
+            (Some("7".to_owned()), Source::Environment)
+        );
+        assert_eq!(
+            source("no_wrap"),
+            (Some("false".to_owned()), Source::CommandLine)
+        );
+        assert_eq!(source("emphasis"), (Some("true".to_owned()), Source::File));
+        assert_eq!(
+            source("headings"),
+            (Some("false".to_owned()), Source::Default)
+        );
+        std::env::remove_var("VOTD_TIMEOUT");
+    }
+
+    #[test]
+    fn invalid_overrides_are_reported() {
+        for setting in ["no_wrap", "no_wrap=maybe", "timeout=soon", "colour=red"] {
+            match Config::load_with_overrides(None, &overrides(&[setting])) {
+                Err(VotdError::Config(message)) => {
+                    assert!(message.starts_with("--set "), "{}", message)
+                }
+                other => panic!("{} gave {:?}", setting, other),
+            }
+        }
+    }
+
+    #[test]
+    fn numbers_are_checked_against_their_settings_type() {
+        let (_dir, path) = write_config("");
+        Config::set(&path, "timeout", "5000000000").unwrap();
+        let config = Config::load(&path).unwrap();
+        assert_eq!(config.timeout, Some(5_000_000_000));
+
+        match Config::set(&path, "retries", "5000000000") {
+            Err(VotdError::Config(message)) => assert_eq!(
+                message,
+                "invalid value \"5000000000\" for retries; expected a whole number up to 4294967295"
+            ),
+            other => panic!("retries=5000000000 gave {:?}", other),
+        }
+        for value in ["-1", "18446744073709551615"] {
+            assert!(Config::set(&path, "timeout", value).is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn set_keeps_the_rest_of_the_file() {
+        let (_dir, path) = write_config("# My settings\ntimeout = 5\n");
+        Config::set(&path, "show_translation", "yes").unwrap();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(contents.starts_with("# My settings\ntimeout = 5\n"));
+        let config = Config::load(&path).unwrap();
+        assert_eq!(config.show_translation, Some(true));
+        assert_eq!(config.timeout, Some(5));
+    }
+}
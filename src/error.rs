@@ -0,0 +1,171 @@
+use crate::reference::ReferenceError;
+use std::fmt;
+
+/// Errors that can occur while looking up a verse.
+#[derive(Debug)]
+pub enum VotdError {
+    /// The request took longer than the configured timeout.
+    Timeout,
+    /// The server responded with an error status.
+    Status(reqwest::StatusCode),
+    /// The provider has no such passage, e.g. because the verse doesn't
+    /// exist in its translation.
+    NotFound(String),
+    /// A connection to the server couldn't be established.
+    Connect(reqwest::Error),
+    /// Any other error from the HTTP client.
+    Http(reqwest::Error),
+    /// The server responded successfully, but the response couldn't be
+    /// understood.
+    MalformedResponse(String),
+    /// Reading from or writing to the cache failed.
+    Cache(std::io::Error),
+    /// The requested reference couldn't be parsed.
+    InvalidReference(ReferenceError),
+    /// No provider exists with the given name.
+    UnknownProvider(String),
+    /// A file being imported into the offline store couldn't be parsed.
+    Import(String),
+    /// Reading from or writing to the offline store failed.
+    Store(std::io::Error),
+    /// The configuration file couldn't be read or parsed.
+    Config(String),
+    /// Reading from or writing to the bookmarks file failed.
+    Bookmarks(std::io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, VotdError>;
+
+/// The exit codes used by the `votd` command, following BSD's `sysexits.h`
+/// so scripts can tell e.g. being offline apart from asking for a verse that
+/// doesn't exist. Anything else that goes wrong exits with 1.
+pub mod sysexits {
+    /// The command was used incorrectly, e.g. with a bad option.
+    pub const USAGE: i32 = 64;
+    /// The reference, or a file being imported, is invalid.
+    pub const DATA_ERR: i32 = 65;
+    /// The passage (or an imported translation to search) doesn't exist.
+    pub const NO_INPUT: i32 = 66;
+    /// The provider couldn't be reached, or responded with an error.
+    pub const UNAVAILABLE: i32 = 69;
+    /// A file couldn't be created, e.g. because its location is unknown.
+    pub const CANT_CREAT: i32 = 73;
+    /// Reading from or writing to the cache or offline store failed.
+    pub const IO_ERR: i32 = 74;
+    /// The provider took too long to respond; trying again later may work.
+    pub const TEMP_FAIL: i32 = 75;
+    /// The provider's response couldn't be understood.
+    pub const PROTOCOL: i32 = 76;
+    /// The configuration is invalid.
+    pub const CONFIG: i32 = 78;
+}
+
+impl VotdError {
+    /// The exit code (see [`sysexits`]) the `votd` command exits with after
+    /// this error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            VotdError::Timeout => sysexits::TEMP_FAIL,
+            VotdError::Status(_) | VotdError::Connect(_) | VotdError::Http(_) => {
+                sysexits::UNAVAILABLE
+            }
+            VotdError::NotFound(_) => sysexits::NO_INPUT,
+            VotdError::MalformedResponse(_) => sysexits::PROTOCOL,
+            VotdError::Cache(_) | VotdError::Store(_) | VotdError::Bookmarks(_) => sysexits::IO_ERR,
+            VotdError::InvalidReference(_) | VotdError::Import(_) => sysexits::DATA_ERR,
+            VotdError::UnknownProvider(_) => sysexits::USAGE,
+            VotdError::Config(_) => sysexits::CONFIG,
+        }
+    }
+
+    /// Whether the provider couldn't be reached at all, e.g. because there's
+    /// no Internet connection, as opposed to it giving a bad response.
+    pub fn is_network(&self) -> bool {
+        matches!(
+            self,
+            VotdError::Timeout | VotdError::Connect(_) | VotdError::Http(_)
+        )
+    }
+
+    /// Whether the same request might succeed if it's tried again: the
+    /// provider couldn't be reached, or it's overloaded or having problems.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            VotdError::Status(status) => {
+                status.is_server_error() || *status == reqwest::StatusCode::TOO_MANY_REQUESTS
+            }
+            e => e.is_network(),
+        }
+    }
+}
+
+impl fmt::Display for VotdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VotdError::Timeout => write!(f, "timeout exceeded"),
+            VotdError::Status(status) => write!(f, "server returned an error ({})", status),
+            VotdError::NotFound(passage) => write!(
+                f,
+                "{} wasn't found; is the verse you requested valid?",
+                passage
+            ),
+            VotdError::Connect(_) => write!(
+                f,
+                "couldn't connect to server; are you connected to the Internet?"
+            ),
+            VotdError::Http(e) => write!(f, "{}", e),
+            VotdError::MalformedResponse(msg) => write!(f, "malformed response: {}", msg),
+            VotdError::Cache(e) => write!(f, "cache error: {}", e),
+            VotdError::InvalidReference(e) => write!(f, "invalid reference: {}", e),
+            VotdError::UnknownProvider(name) => write!(
+                f,
+                "unknown provider {:?}; expected one of: {}",
+                name,
+                crate::provider::PROVIDER_NAMES.join(", ")
+            ),
+            VotdError::Import(msg) => write!(f, "couldn't import: {}", msg),
+            VotdError::Store(e) => write!(f, "offline store error: {}", e),
+            VotdError::Config(msg) => write!(f, "config error: {}", msg),
+            VotdError::Bookmarks(e) => write!(f, "bookmarks error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for VotdError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            VotdError::Connect(e) | VotdError::Http(e) => Some(e),
+            VotdError::Cache(e) | VotdError::Store(e) | VotdError::Bookmarks(e) => Some(e),
+            VotdError::InvalidReference(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for VotdError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            VotdError::Timeout
+        } else if let Some(status) = e.status() {
+            VotdError::Status(status)
+        } else if e.is_connect() {
+            VotdError::Connect(e)
+        } else if e.is_decode() {
+            VotdError::MalformedResponse(e.to_string())
+        } else {
+            VotdError::Http(e)
+        }
+    }
+}
+
+impl From<ReferenceError> for VotdError {
+    fn from(e: ReferenceError) -> Self {
+        VotdError::InvalidReference(e)
+    }
+}
+
+impl From<std::io::Error> for VotdError {
+    fn from(e: std::io::Error) -> Self {
+        VotdError::Cache(e)
+    }
+}
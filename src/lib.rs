@@ -0,0 +1,38 @@
+//! Look up Bible verses, including the verse-of-the-day, from the NET Bible
+//! API or from a translation imported for offline use.
+//!
+//! ```no_run
+//! let client = votd::VerseClient::new(std::time::Duration::from_secs(2))?;
+//! let verse = client.lookup("John 3:16")?;
+//! println!("{}\n{}", verse.title(), verse.text());
+//! # Ok::<(), votd::VotdError>(())
+//! ```
+
+pub mod bookmarks;
+pub mod books;
+mod cache;
+mod client;
+pub mod clock;
+pub mod config;
+mod error;
+pub mod markup;
+pub mod network;
+pub mod output;
+pub mod provider;
+pub mod reference;
+pub mod render;
+pub mod store;
+pub mod template;
+mod verse;
+pub mod versification;
+
+pub use bookmarks::Bookmarks;
+pub use cache::{CacheEntry, CacheHealth, CacheKey, Fallback, VerseCache};
+pub use client::VerseClient;
+pub use config::Config;
+pub use error::{sysexits, Result, VotdError};
+pub use provider::VerseProvider;
+pub use reference::Reference;
+pub use store::BibleStore;
+pub use verse::{Verse, VerseText};
+pub use versification::Canon;
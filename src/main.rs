@@ -1,36 +1,95 @@
+mod reader;
+
 use argh::FromArgs;
-use const_format::concatcp;
-use filetime::FileTime;
-use serde_derive::{Deserialize, Serialize};
-use std::io::{Read, Seek};
-use std::path::PathBuf;
+use chrono::{DateTime, Days, Local, NaiveDate, Utc};
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
 use std::time::Duration;
+use votd::clock::Timezone;
+use votd::config::Source;
+use votd::network::NetworkOptions;
+use votd::output::{CacheInfo, Document, OutputFormat};
+use votd::render::{NumberStyle, TextLayout};
+use votd::store::ImportFormat;
+use votd::template::Template;
+use votd::{
+    provider, sysexits, BibleStore, CacheEntry, CacheHealth, CacheKey, Canon, Config, Fallback,
+    Reference, Verse, VerseCache, VerseClient, VotdError,
+};
 
 #[derive(FromArgs)]
 /// Retrieve the verse-of-the-day or a specified verse from NET Bible. Verses
-/// are case-insensitive, and some short names are acceptable (based on the NET
-/// Bible API, not the CLI). "random" and "votd" are accepted verses, and do
-/// what they sound like.
+/// are case-insensitive, and common abbreviations, ranges, and lists are
+/// accepted (e.g. "Jn 3:16", "1 Cor 13:4-7", or "Gen 1:30-2:3; Rom 8:28").
+/// "votd <verse>" is short for "votd get <verse>", and "votd" alone for
+/// "votd today". Options go before the subcommand. Defaults for them can be
+/// set in the config file or as VOTD_<OPTION> environment variables, e.g.
+/// VOTD_TIMEOUT=5.
 struct VerseOpts {
-    /// disable reading from/writing to the cache (only affects VotD)
+    /// disable reading from/writing to the cache
     #[argh(switch, short = 'n')]
     no_cache: bool,
 
-    /// get the current VotD from the web (not cache), then write it to cache
+    /// look the verse(s) up from the web (not cache), then write them to
+    /// cache
     #[argh(switch, short = 'r')]
     refresh_cache: bool,
 
+    /// show an out-of-date cached verse-of-the-day straight away, and fetch
+    /// the new one in the background for next time
+    #[argh(switch)]
+    background_refresh: bool,
+
+    /// when to show the last cached verse(s) if they can't be looked up:
+    /// never, on-network-error (the default), or always
+    #[argh(option)]
+    fallback: Option<Fallback>,
+
     /// only display the text of the verse(s), with no title before
     #[argh(switch, short = 'o')]
     only_verse: bool,
 
-    /// print the translation (NET) after the verse name
+    /// print the translation (e.g. NET) after the verse name
     #[argh(switch)]
     show_translation: bool,
 
-    /// specify a timeout to quit the request after (in seconds); defaults to 2
-    #[argh(option, default = "2", short = 't')]
-    timeout: u64,
+    /// specify a timeout to quit the request after (in seconds), from
+    /// connecting to the end of the response; defaults to 2, and is also
+    /// the connect timeout unless connect_timeout is set
+    #[argh(option, short = 't')]
+    timeout: Option<u64>,
+
+    /// the source to look verses up from; defaults to "net" (the NET Bible
+    /// API at labs.bible.org)
+    #[argh(option, short = 'p')]
+    provider: Option<String>,
+
+    /// the imported translation to look verses up in offline; defaults to
+    /// the provider's translation
+    #[argh(option)]
+    translation: Option<String>,
+
+    /// the canon to check references against: protestant, catholic (adds the
+    /// deuterocanon), or orthodox; defaults to protestant
+    #[argh(option)]
+    canon: Option<Canon>,
+
+    /// import a translation from an OSIS, USFM, JSON, or CSV file so verses
+    /// can be looked up offline, then exit
+    #[argh(option)]
+    import: Option<PathBuf>,
+
+    /// the format of the file given to --import (osis, usfm, json, or csv);
+    /// guessed from its extension by default
+    #[argh(option)]
+    import_format: Option<String>,
+
+    /// the translation to store an --import as; defaults to the provider's
+    /// translation
+    #[argh(option)]
+    import_as: Option<String>,
 
     /// display the program version and exit
     #[argh(switch, short = 'v')]
@@ -40,180 +99,1110 @@ struct VerseOpts {
     #[argh(switch, short = 'w')]
     no_wrap: bool,
 
+    /// show verse numbers inline with the text; on by default for whole
+    /// chapters
+    #[argh(switch)]
+    verse_numbers: Option<bool>,
+
+    /// how to write verse numbers: superscript or brackets; defaults to
+    /// superscript
+    #[argh(option)]
+    number_style: Option<NumberStyle>,
+
+    /// the timezone whose midnight starts a new verse-of-the-day: provider
+    /// (the default), local, or a name like Europe/London
+    #[argh(option)]
+    timezone: Option<Timezone>,
+
+    /// write the passage as json, yaml, markdown, plain, or html instead of
+    /// laying it out for the terminal
+    #[argh(option)]
+    format: Option<OutputFormat>,
+
+    /// lay the passage out with a template instead; see the README for the
+    /// fields and conditionals available
+    #[argh(option)]
+    template: Option<String>,
+
+    /// print each verse on its own line
+    #[argh(switch)]
+    one_per_line: bool,
+
+    /// show text the source emphasizes in bold or italics using terminal
+    /// styles
+    #[argh(switch)]
+    emphasis: bool,
+
+    /// show section headings (e.g. "The New Birth") above the verses they
+    /// start
+    #[argh(switch)]
+    headings: bool,
+
+    /// the page of a chapter or book to show, starting from 1; defaults to 1
+    #[argh(option)]
+    page: Option<usize>,
+
+    /// the most verses to show on each page; by default, each chapter is a
+    /// page
+    #[argh(option)]
+    limit: Option<usize>,
+
+    /// print output taller than the terminal straight to it, instead of
+    /// through $PAGER
+    #[argh(switch)]
+    no_pager: bool,
+
+    /// override a setting for this run, e.g. --set no_wrap=false; may be
+    /// repeated, and takes precedence over the config file and environment
+    #[argh(option)]
+    set: Vec<String>,
+
+    #[argh(subcommand)]
+    command: Option<Command>,
+
+    #[argh(positional)]
+    verse: Vec<String>,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum Command {
+    Today(TodayCommand),
+    Get(GetCommand),
+    Random(RandomCommand),
+    Search(SearchCommand),
+    Read(ReadCommand),
+    Cache(CacheCommand),
+    Config(ConfigCommand),
+}
+
+#[derive(FromArgs)]
+/// Show the verse-of-the-day.
+#[argh(subcommand, name = "today")]
+struct TodayCommand {}
+
+#[derive(FromArgs)]
+/// Look up a verse, passage, or list of them.
+#[argh(subcommand, name = "get")]
+struct GetCommand {
     #[argh(positional)]
     verse: Vec<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct Verse {
-    title: String,
-    text: String,
+#[derive(FromArgs)]
+/// Show a random verse.
+#[argh(subcommand, name = "random")]
+struct RandomCommand {}
+
+#[derive(FromArgs)]
+/// Search an imported translation for verses containing every given word.
+#[argh(subcommand, name = "search")]
+struct SearchCommand {
+    /// the most verses to show; defaults to 20
+    #[argh(option, default = "20")]
+    limit: usize,
+
+    #[argh(positional)]
+    words: Vec<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct ApiVerse {
-    bookname: String,
-    chapter: String,
-    verse: String,
-    text: String,
+#[derive(FromArgs)]
+/// Read a chapter at a time in a full-screen reader, starting with the one a
+/// passage is in. Press n and p for the next and previous chapters, : to go
+/// to a passage, / to search an imported translation, m to bookmark the top
+/// verse, ' to list bookmarks, and q to quit.
+#[argh(subcommand, name = "read")]
+struct ReadCommand {
+    #[argh(positional)]
+    verse: Vec<String>,
 }
 
-const VERSE_URL: &str = "https://labs.bible.org/api/?type=json";
-const URL_PARSE_ERROR: &str = concatcp!(VERSE_URL, " should be a valid URL");
-const CACHE_EXPIRE_TIME: i64 = 21600; // 1/4 a day, in seconds
+#[derive(FromArgs)]
+/// Manage the verse-of-the-day cache.
+#[argh(subcommand, name = "cache")]
+struct CacheCommand {
+    #[argh(subcommand)]
+    action: CacheAction,
+}
 
-fn cache_file_path() -> Option<PathBuf> {
-    directories::BaseDirs::new().map(|dirs| dirs.cache_dir().join("votd-cli-cache.txt"))
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum CacheAction {
+    Status(CacheStatusCommand),
+    Path(CachePathCommand),
+    Clear(CacheClearCommand),
+    Verify(CacheVerifyCommand),
+    Prune(CachePruneCommand),
+    Warm(CacheWarmCommand),
 }
 
-fn fetch_verse(verse: Option<&str>, timeout: Duration) -> reqwest::Result<Verse> {
-    let url = reqwest::Url::parse_with_params(VERSE_URL, &[("passage", verse.unwrap_or("votd"))])
-        .expect(URL_PARSE_ERROR);
-    let client = reqwest::blocking::Client::builder()
-        .timeout(timeout)
-        .build()?;
-    // The API returns status code 400 and a blank page when given an invalid
-    // verse to look-up. To work around this, `error_for_status()` is used for
-    // an early return instead of trying to parse an empty page as JSON.
-    let verses = client
-        .get(url)
-        .header(
-            reqwest::header::HeaderName::from_static("user-agent"),
-            "Mozilla/5.0 Gecko/20100101 Firefox/130.0"
-        )
-        .send()?
-        .error_for_status()?
-        .json::<Vec<ApiVerse>>()?;
-    assert!(!verses.is_empty(), "No verses returned");
-    let book = &verses[0].bookname;
-    let chapter = verses[0]
-        .chapter
-        .parse::<i32>()
-        .expect("Chapters should be valid integers");
-    let verse_start = verses[0]
-        .verse
-        .parse::<i32>()
-        .expect("Verses should be valid integers");
-    let verse_end = verses[verses.len() - 1]
-        .verse
-        .parse::<i32>()
-        .expect("Verses should be valid integers");
-    Ok(Verse {
-        title: if verse_start == verse_end {
-            format!("{} {}:{}", book, chapter, verse_start)
-        } else {
-            format!("{} {}:{}-{}", book, chapter, verse_start, verse_end)
-        },
-        text: verses.iter().fold("".to_owned(), |acc, e| acc + &e.text),
-    })
+#[derive(FromArgs)]
+/// Show what's in the cache.
+#[argh(subcommand, name = "status")]
+struct CacheStatusCommand {}
+
+#[derive(FromArgs)]
+/// Print where the cache file is.
+#[argh(subcommand, name = "path")]
+struct CachePathCommand {}
+
+#[derive(FromArgs)]
+/// Delete the cache file.
+#[argh(subcommand, name = "clear")]
+struct CacheClearCommand {}
+
+#[derive(FromArgs)]
+/// Check the cache file can be read, rebuilding it from whatever can be
+/// salvaged if not.
+#[argh(subcommand, name = "verify")]
+struct CacheVerifyCommand {}
+
+#[derive(FromArgs)]
+/// Remove out-of-date verses-of-the-day from the cache.
+#[argh(subcommand, name = "prune")]
+struct CachePruneCommand {
+    /// also remove passages that haven't been used in this many days
+    #[argh(option)]
+    unused_days: Option<u64>,
 }
 
-fn unwrap_error<T>(res: reqwest::Result<T>) -> T {
+#[derive(FromArgs)]
+/// Fetch upcoming verses-of-the-day into the cache, if the provider can look
+/// them up ahead of time (otherwise, just today's).
+#[argh(subcommand, name = "warm")]
+struct CacheWarmCommand {
+    /// how many days to fetch, starting with today; defaults to 7
+    #[argh(option, default = "7")]
+    days: u32,
+}
+
+#[derive(FromArgs)]
+/// Manage the config file.
+#[argh(subcommand, name = "config")]
+struct ConfigCommand {
+    #[argh(subcommand)]
+    action: ConfigAction,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum ConfigAction {
+    Show(ConfigShowCommand),
+    Get(ConfigGetCommand),
+    Set(ConfigSetCommand),
+    Path(ConfigPathCommand),
+}
+
+#[derive(FromArgs)]
+/// Show every setting's effective value and where it comes from.
+#[argh(subcommand, name = "show")]
+struct ConfigShowCommand {}
+
+#[derive(FromArgs)]
+/// Print a setting's value in the config file.
+#[argh(subcommand, name = "get")]
+struct ConfigGetCommand {
+    #[argh(positional)]
+    name: String,
+}
+
+#[derive(FromArgs)]
+/// Change a setting in the config file.
+#[argh(subcommand, name = "set")]
+struct ConfigSetCommand {
+    #[argh(positional)]
+    name: String,
+
+    #[argh(positional, greedy)]
+    value: Vec<String>,
+}
+
+#[derive(FromArgs)]
+/// Print where the config file is.
+#[argh(subcommand, name = "path")]
+struct ConfigPathCommand {}
+
+/// What was asked to be shown.
+enum Request {
+    Today,
+    Passage(String),
+    Random,
+    Search { query: String, limit: usize },
+    Read(String),
+    Cache(CacheAction),
+}
+
+const DEFAULT_TIMEOUT: u64 = 2;
+const DEFAULT_PROVIDER: &str = "net";
+
+fn unwrap_error<T>(res: votd::Result<T>) -> T {
     match res {
         Ok(x) => x,
         Err(e) => {
-            if e.is_timeout() {
-                eprintln!("Error: timeout exceeded");
-            } else if e.is_status() {
-                eprintln!("Server returned an error; is the verse you requested valid?");
-            } else if e.is_connect() {
-                eprintln!("Couldn't connect to server; are you connected to the Internet?");
-            } else {
-                eprintln!("Error: {}", e);
+            eprintln!("Error: {}", e);
+            std::process::exit(e.exit_code());
+        }
+    }
+}
+
+fn import(
+    dir: &Path,
+    translation: &str,
+    path: &Path,
+    format: Option<&str>,
+) -> votd::Result<votd::store::ImportSummary> {
+    let format = match format {
+        Some(format) => format.parse()?,
+        None => ImportFormat::from_path(path).ok_or_else(|| {
+            VotdError::Import(format!(
+                "can't guess the format of {}; pass --import-format",
+                path.display()
+            ))
+        })?,
+    };
+    BibleStore::import(dir, translation, path, format)
+}
+
+/// Fills in options not given on the command line from `config`.
+fn apply_config(args: &mut VerseOpts, config: Config) {
+    let switch = |arg: &mut bool, setting: Option<bool>| *arg = *arg || setting == Some(true);
+    switch(&mut args.no_cache, config.no_cache);
+    switch(&mut args.background_refresh, config.background_refresh);
+    switch(&mut args.only_verse, config.only_verse);
+    switch(&mut args.show_translation, config.show_translation);
+    switch(&mut args.no_wrap, config.no_wrap);
+    switch(&mut args.one_per_line, config.one_per_line);
+    switch(&mut args.emphasis, config.emphasis);
+    switch(&mut args.headings, config.headings);
+    switch(&mut args.no_pager, config.no_pager);
+    // Left unset if neither sets it, so whole chapters can default it on.
+    args.verse_numbers = args.verse_numbers.or(config.verse_numbers);
+    args.fallback = args.fallback.or(config.fallback);
+    args.timeout = args.timeout.or(config.timeout);
+    args.provider = args.provider.take().or(config.provider);
+    args.translation = args.translation.take().or(config.translation);
+    args.canon = args.canon.or(config.canon);
+    args.number_style = args.number_style.or(config.number_style);
+    args.timezone = args.timezone.or(config.timezone);
+    args.format = args.format.or(config.format);
+    args.template = args.template.take().or(config.template);
+    args.limit = args.limit.or(config.limit);
+}
+
+/// The network settings from `config`, with requests giving up after
+/// `timeout`.
+fn network_options(config: Config, timeout: Duration) -> NetworkOptions {
+    let defaults = NetworkOptions::with_timeout(timeout);
+    NetworkOptions {
+        connect_timeout: config
+            .connect_timeout
+            .map_or(defaults.connect_timeout, Duration::from_secs),
+        retries: config.retries.unwrap_or(defaults.retries),
+        retry_delay: config
+            .retry_delay_ms
+            .map_or(defaults.retry_delay, Duration::from_millis),
+        proxy: config.proxy,
+        ca_bundle: config.ca_bundle,
+        tls: config.tls.unwrap_or(defaults.tls),
+        ..defaults
+    }
+}
+
+/// Prints `message` and exits with `code` (see [`sysexits`]).
+fn fail(code: i32, message: &str) -> ! {
+    eprintln!("Error: {}", message);
+    std::process::exit(code);
+}
+
+/// Parses the command line, like `argh::from_env` does, but exits with
+/// [`sysexits::USAGE`] if it's invalid.
+fn parse_args() -> VerseOpts {
+    let strings: Vec<String> = std::env::args_os()
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect();
+    let name = strings
+        .first()
+        .and_then(|cmd| Path::new(cmd).file_name())
+        .and_then(|name| name.to_str())
+        .unwrap_or("votd");
+    let rest: Vec<&str> = strings.iter().skip(1).map(String::as_str).collect();
+    VerseOpts::from_args(&[name], &rest).unwrap_or_else(|early_exit| match early_exit.status {
+        Ok(()) => {
+            println!("{}", early_exit.output);
+            std::process::exit(0);
+        }
+        Err(()) => fail(
+            sysexits::USAGE,
+            &format!(
+                "{}\nRun {} --help for more information.",
+                early_exit.output.trim_end(),
+                name
+            ),
+        ),
+    })
+}
+
+/// Handles `votd config ...`.
+fn config_command(action: ConfigAction, overrides: &[String]) {
+    let path = Config::default_path()
+        .unwrap_or_else(|| fail(sysexits::CONFIG, "can't determine where the config file is"));
+    match action {
+        ConfigAction::Path(_) => println!("{}", path.display()),
+        ConfigAction::Show(_) => {
+            for setting in unwrap_error(Config::describe(Some(&path), overrides)) {
+                let source = match setting.source {
+                    Source::Default => "default",
+                    Source::File => "config file",
+                    Source::Environment => "environment",
+                    Source::CommandLine => "command line",
+                };
+                match setting.value {
+                    Some(value) => println!("{} = {} ({})", setting.name, value, source),
+                    None => println!("{} is unset", setting.name),
+                }
+            }
+        }
+        ConfigAction::Get(get) => {
+            if let Some(value) = unwrap_error(Config::get(&path, &get.name)) {
+                println!("{}", value);
+            }
+        }
+        ConfigAction::Set(set) => {
+            unwrap_error(Config::set(&path, &set.name, &set.value.join(" ")));
+        }
+    }
+}
+
+/// Describes a number of seconds, e.g. "5m" or "3h".
+fn describe_age(seconds: u64) -> String {
+    match seconds {
+        0..=59 => format!("{}s", seconds),
+        60..=3599 => format!("{}m", seconds / 60),
+        3600..=86399 => format!("{}h", seconds / 3600),
+        _ => format!("{}d", seconds / 86400),
+    }
+}
+
+fn describe_health(health: CacheHealth) -> String {
+    match health {
+        CacheHealth::Ok => "ok".to_owned(),
+        CacheHealth::Damaged { kept, dropped } => {
+            format!("damaged ({} entries salvaged, {} lost)", kept, dropped)
+        }
+        CacheHealth::Unreadable => "unreadable".to_owned(),
+        CacheHealth::Migrated { from } => format!("written in the version {} layout", from),
+        CacheHealth::Unsupported { version } => format!(
+            "written in the version {} layout by a newer version of votd",
+            version
+        ),
+    }
+}
+
+/// Handles `votd cache ...`; `open` opens the cache with the configured
+/// size and timezone.
+fn cache_command(action: CacheAction, path: &Path, open: impl Fn() -> VerseCache) {
+    match action {
+        CacheAction::Path(_) => println!("{}", path.display()),
+        CacheAction::Clear(_) => unwrap_error(VerseCache::clear(path)),
+        CacheAction::Status(_) => {
+            let cache = open();
+            let now = std::time::SystemTime::now();
+            println!("Path: {}", path.display());
+            println!("Size: {} bytes", unwrap_error(cache.file_size()));
+            println!("Health: {}", describe_health(cache.health()));
+            println!("Entries: {}", cache.len());
+            for (key, entry) in cache.entries() {
+                let age = now
+                    .duration_since(entry.written_at())
+                    .map_or(0, |age| age.as_secs());
+                let what = match (key.reference(), entry.date) {
+                    (Some(reference), _) => reference.to_owned(),
+                    (None, Some(date)) => {
+                        format!("Verse of the Day for {} ({})", date, entry.verse.title())
+                    }
+                    (None, None) => format!("Verse of the Day ({})", entry.verse.title()),
+                };
+                let stale = if cache.is_current(&key, entry) {
+                    ""
+                } else {
+                    ", out of date"
+                };
+                println!(
+                    "  {} {}: cached {} ago{}",
+                    key.translation(),
+                    what,
+                    describe_age(age),
+                    stale
+                );
+            }
+        }
+        CacheAction::Verify(_) => {
+            let mut cache = open();
+            let health = cache.health();
+            println!("{}", describe_health(health));
+            match health {
+                CacheHealth::Ok => {}
+                CacheHealth::Unsupported { .. } => std::process::exit(sysexits::DATA_ERR),
+                _ => {
+                    unwrap_error(cache.save());
+                    println!("Rewrote the cache with {} entries", cache.len());
+                }
+            }
+        }
+        CacheAction::Prune(prune) => {
+            let mut cache = open();
+            let removed = cache.prune(
+                prune
+                    .unused_days
+                    .map(|days| Duration::from_secs(days * 86400)),
+            );
+            unwrap_error(cache.save());
+            println!("Removed {} entries; {} remain", removed, cache.len());
+        }
+        // Handled by `warm_cache`, which needs a client.
+        CacheAction::Warm(_) => unreachable!(),
+    }
+}
+
+/// Handles `votd cache warm`, fetching the verses-of-the-day for `days` days
+/// from `today` that aren't already cached.
+fn warm_cache(mut cache: VerseCache, client: &VerseClient, today: NaiveDate, days: u32) {
+    let translation = client.provider().translation();
+    let mut fetched = 0;
+    let key = CacheKey::votd(translation);
+    if days > 0 && cache.get(&key).is_none() {
+        cache.insert(&key, &unwrap_error(client.votd()));
+        fetched += 1;
+    }
+    for offset in 1..days {
+        let date = today + Days::new(offset.into());
+        let key = CacheKey::votd_on(translation, date);
+        if cache.get_stale(&key).is_some() {
+            continue;
+        }
+        match unwrap_error(client.votd_on(date)) {
+            Some(verse) => {
+                cache.insert(&key, &verse);
+                fetched += 1;
+            }
+            None => {
+                eprintln!(
+                    "The {} provider can't look up verses-of-the-day ahead of time, so only today's was fetched",
+                    client.provider().name()
+                );
+                break;
             }
-            std::process::exit(1);
         }
     }
+    unwrap_error(cache.save());
+    println!("Fetched {} verses-of-the-day", fetched);
+}
+
+/// Starts `votd -r today` in the background, with its output discarded, so
+/// the cached verse-of-the-day is current next time.
+fn refresh_in_background(args: &VerseOpts, translation: &str) {
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(_) => return,
+    };
+    let mut command = std::process::Command::new(exe);
+    command.args(["-r", "--translation", translation]);
+    if let Some(provider) = &args.provider {
+        command.args(["-p", provider]);
+    }
+    if let Some(timeout) = args.timeout {
+        command.args(["-t", &timeout.to_string()]);
+    }
+    if let Some(timezone) = args.timezone {
+        command.args(["--timezone", &timezone.to_string()]);
+    }
+    for setting in &args.set {
+        command.args(["--set", setting]);
+    }
+    command
+        .arg("today")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    // If it can't be started, the verse will just be fetched next time.
+    let _ = command.spawn();
 }
 
 fn main() {
-    let args: VerseOpts = argh::from_env();
+    let mut args = parse_args();
     if args.version {
         println!("VotD v{}", env!("CARGO_PKG_VERSION"));
         return;
     }
 
-    let verse_requested = if !args.verse.is_empty() {
-        Some(args.verse.join(" "))
-    } else {
-        None
-    };
-
-    let timeout = Duration::from_secs(args.timeout);
-
-    let mut cache = if verse_requested.is_none() && !args.no_cache {
-        if let Some(path) = cache_file_path() {
-            let mut cache_file = std::fs::OpenOptions::new()
-                .read(true)
-                .write(true)
-                .create(true)
-                .truncate(false)
-                .append(false)
-                .open(path)
-                .unwrap();
-            cache_file.rewind().unwrap();
-            let metadata = cache_file.metadata().unwrap();
-            let stamp = FileTime::from_last_modification_time(&metadata).seconds();
-            let now = FileTime::now().seconds();
-            Some((
-                cache_file,
-                now - stamp <= CACHE_EXPIRE_TIME && !args.refresh_cache,
-            ))
-        } else {
-            println!("Can't determine where to place a cache file. Skipping.");
-            None
+    let verse = Some(args.verse.join(" ")).filter(|verse| !verse.trim().is_empty());
+    let request = match (args.command.take(), verse) {
+        (Some(_), Some(verse)) => fail(
+            sysexits::USAGE,
+            &format!("unexpected {:?} before the subcommand", verse),
+        ),
+        (Some(Command::Config(config)), None) => return config_command(config.action, &args.set),
+        (Some(Command::Cache(cache)), None) => Request::Cache(cache.action),
+        (Some(Command::Today(_)), None) | (None, None) => Request::Today,
+        (Some(Command::Get(get)), None) => match Some(get.verse.join(" ")) {
+            Some(verse) if !verse.trim().is_empty() => Request::Passage(verse),
+            _ => fail(sysexits::USAGE, "no verse given to look up"),
+        },
+        (Some(Command::Read(read)), None) => match Some(read.verse.join(" ")) {
+            Some(verse) if verse.trim().is_empty() => {
+                fail(sysexits::USAGE, "no passage given to read")
+            }
+            // Without a terminal to take over, just print the passage.
+            Some(verse) if terminal_size::terminal_size().is_none() => Request::Passage(verse),
+            verse => Request::Read(verse.unwrap_or_default()),
+        },
+        (Some(Command::Random(_)), None) => Request::Random,
+        (Some(Command::Search(search)), None) => Request::Search {
+            query: search.words.join(" "),
+            limit: search.limit,
+        },
+        // Kept from before there were subcommands.
+        (None, Some(verse)) if verse.trim().eq_ignore_ascii_case("votd") => Request::Today,
+        (None, Some(verse)) => Request::Passage(verse),
+    };
+
+    let config_path = Config::default_path();
+    let config = unwrap_error(Config::load_with_overrides(
+        config_path.as_deref(),
+        &args.set,
+    ));
+    let cache_entries = config
+        .cache_entries
+        .unwrap_or(VerseCache::DEFAULT_MAX_ENTRIES);
+    apply_config(&mut args, config.clone());
+    let template = args
+        .template
+        .as_deref()
+        .map(|template| match template.parse::<Template>() {
+            Ok(template) => template,
+            Err(e) => fail(sysexits::USAGE, &format!("invalid template: {}", e)),
+        });
+
+    let timeout = Duration::from_secs(args.timeout.unwrap_or(DEFAULT_TIMEOUT));
+    let network = network_options(config, timeout);
+    let provider = args.provider.as_deref().unwrap_or(DEFAULT_PROVIDER);
+    let client = VerseClient::from_boxed(unwrap_error(provider::by_name(provider, &network)))
+        .with_canon(args.canon.unwrap_or_default());
+    let translation = match &args.translation {
+        Some(translation) => translation.to_ascii_uppercase(),
+        None => client.provider().translation().to_owned(),
+    };
+    let store_dir = BibleStore::default_dir();
+
+    if let Some(path) = &args.import {
+        let dir = store_dir.as_deref().unwrap_or_else(|| {
+            fail(
+                sysexits::CANT_CREAT,
+                "can't determine where to store imported translations",
+            )
+        });
+        let translation = args.import_as.as_deref().unwrap_or(&translation);
+        let summary = unwrap_error(import(
+            dir,
+            translation,
+            path,
+            args.import_format.as_deref(),
+        ));
+        println!(
+            "Imported {} verses in {} books as {}",
+            summary.verses,
+            summary.books,
+            translation.to_ascii_uppercase()
+        );
+        return;
+    }
+
+    let cache_path = VerseCache::default_path();
+    let timezone = args.timezone.unwrap_or_default();
+    let provider_timezone = client.provider().timezone();
+    let open_cache = |path: &Path| {
+        let cache = unwrap_error(VerseCache::open(path, cache_entries));
+        cache.with_timezone(timezone, provider_timezone)
+    };
+    let request = match request {
+        Request::Cache(action) => {
+            let path = cache_path.as_deref().unwrap_or_else(|| {
+                fail(
+                    sysexits::CANT_CREAT,
+                    "can't determine where the cache file is",
+                )
+            });
+            if let CacheAction::Warm(warm) = action {
+                let today = timezone.date(provider_timezone, Utc::now());
+                let cache = open_cache(path);
+                return warm_cache(cache, &client, today, warm.days);
+            }
+            return cache_command(action, path, || open_cache(path));
         }
-    } else {
-        None
+        request => request,
     };
 
-    let (verse, write_cache) = if let Some((cache_file, true)) = cache.as_mut() {
-        let mut buf = Vec::new();
-        cache_file.read_to_end(&mut buf).unwrap();
-        let res = rmp_serde::from_slice(&buf);
-        cache_file.rewind().unwrap();
-        if let Ok(cached) = res {
-            (cached, false)
+    let store = store_dir
+        .as_deref()
+        .and_then(|dir| unwrap_error(BibleStore::open(dir, &translation)));
+    // Anything the store can't answer comes from the provider, so a
+    // translation that's neither imported nor the provider's can't be shown.
+    let provider_translation = client.provider().translation().to_owned();
+    if store.is_none() && !translation.eq_ignore_ascii_case(&provider_translation) {
+        fail(
+            sysexits::USAGE,
+            &format!(
+                "{} isn't imported, and the {} provider only has {}; import it with --import",
+                translation,
+                client.provider().name(),
+                provider_translation
+            ),
+        );
+    }
+
+    if let Request::Search { query, limit } = &request {
+        let store = store.unwrap_or_else(|| {
+            fail(
+                sysexits::NO_INPUT,
+                &format!(
+                "searching needs an imported translation, and {} isn't imported; import one with --import",
+                translation
+            ),
+            )
+        });
+        let found = unwrap_error(store.search(query, *limit));
+        if found.is_empty() {
+            fail(
+                sysexits::NO_INPUT,
+                &format!("no verses contain {:?}", query),
+            );
+        }
+        let verse = Verse { verses: found };
+        if let Some(format) = args.format {
+            let document = Document::new(&verse, &translation, false, CacheInfo::default());
+            print!("{}", document.render(format));
         } else {
-            // for `cache` to be `Some`, `verse_requested` must be `None` and
-            // `no_cache` must be `false`, so we can write to cache
-            (unwrap_error(fetch_verse(None, timeout)), true)
+            let layout = text_layout(&args);
+            for found in &verse.verses {
+                let line = format!(
+                    "{} {}:{}  {}",
+                    found.book, found.chapter, found.verse, found.text
+                );
+                for line in layout.wrap_text(&line) {
+                    println!("{}", line);
+                }
+            }
+        }
+        return;
+    }
+
+    // The reader searches with its own copy of the store.
+    let search_store = match (&request, &store_dir) {
+        (Request::Read(_), Some(dir)) if store.is_some() => {
+            unwrap_error(BibleStore::open(dir, &translation))
+        }
+        _ => None,
+    };
+    let client = match store {
+        Some(store) => client.with_store(store),
+        None => client,
+    };
+
+    if let Request::Read(passage) = &request {
+        let reference =
+            unwrap_error(Reference::parse_with(passage, client.canon()).map_err(VotdError::from));
+        let mut cache = cache_path
+            .as_deref()
+            .filter(|_| !args.no_cache)
+            .map(open_cache);
+        let lookup: reader::Lookup =
+            Box::new(|reference: &Reference| lookup_cached(&client, cache.as_mut(), reference));
+        let search = search_store.as_ref().map(|store| -> reader::Search {
+            Box::new(move |query: &str| store.search(query, reader::SEARCH_LIMIT))
+        });
+        let bookmarks =
+            votd::Bookmarks::default_path().map(|path| unwrap_error(votd::Bookmarks::open(&path)));
+        let layout = TextLayout {
+            verse_numbers: Some(args.number_style.unwrap_or_default()),
+            ..text_layout(&args)
+        };
+        let reader = unwrap_error(reader::Reader::new(
+            reference.first_verse(),
+            client.canon(),
+            layout,
+            lookup,
+            search,
+            bookmarks,
+        ));
+        if let Err(e) = reader.run() {
+            fail(sysexits::IO_ERR, &format!("terminal error: {}", e));
+        }
+        return;
+    }
+
+    let is_votd = matches!(request, Request::Today);
+    let mut page = None;
+    let mut stored = None;
+    let key = match &request {
+        Request::Passage(passage) => {
+            let reference = unwrap_error(
+                Reference::parse_with(passage, client.canon()).map_err(VotdError::from),
+            );
+            // Chapters are easier to find your way around with numbers,
+            // unless they've been turned off.
+            if reference.is_whole_chapters() {
+                args.verse_numbers.get_or_insert(true);
+            }
+            let (reference, selected) = select_page(&args, reference);
+            page = selected;
+            stored = unwrap_error(client.lookup_stored(&reference))
+                .map(|(verse, translation)| (verse, translation.to_owned()));
+            let key = CacheKey::passage(&provider_translation, &reference);
+            Some((key, Some(reference)))
+        }
+        Request::Today => Some((CacheKey::votd(&provider_translation), None)),
+        _ => None,
+    };
+    // Only what the provider sends is cached, so an imported translation is
+    // never shadowed by text fetched before it was imported.
+    let mut cache = match &key {
+        Some(_) if !args.no_cache && stored.is_none() => {
+            if let Some(path) = &cache_path {
+                Some(open_cache(path))
+            } else {
+                println!("Can't determine where to place a cache file. Skipping.");
+                None
+            }
+        }
+        _ => None,
+    };
+
+    let cached = match (&mut cache, &key) {
+        (Some(cache), Some((key, _))) if !args.refresh_cache => cache.get(key),
+        _ => None,
+    };
+    // An out-of-date verse-of-the-day to show while it's refreshed.
+    let stale = match (&mut cache, &key, &cached) {
+        (Some(cache), Some((key, None)), None)
+            if args.background_refresh && !args.refresh_cache =>
+        {
+            cache.get_stale(key)
+        }
+        _ => None,
+    };
+    let refreshing = stale.is_some();
+    let mut note = None;
+    let mut cache_info = match (&cached, &stale) {
+        (Some(entry), _) => CacheInfo::hit(entry.written_at()),
+        (None, Some(entry)) => {
+            note = Some("out of date; refreshing".to_owned());
+            CacheInfo {
+                stale: true,
+                ..CacheInfo::hit(entry.written_at())
+            }
+        }
+        (None, None) => CacheInfo::default(),
+    };
+    // The translation of whatever answered, which for a passage the store
+    // doesn't hold is the provider's rather than the one asked for.
+    let (verse, translation, write_cache) = match (stored, cached.or(stale), &key) {
+        (Some((verse, translation)), _, _) => (verse, translation, false),
+        (None, Some(entry), _) => (entry.verse, provider_translation, false),
+        (None, None, Some((key, reference))) => {
+            let fetched = match reference {
+                Some(reference) => client.provider().lookup(reference),
+                None => client.votd(),
+            };
+            match fetched {
+                Ok(verse) => (verse, provider_translation, true),
+                Err(e) => {
+                    // Show whatever was cached last rather than nothing.
+                    let fallback = match &mut cache {
+                        Some(cache) if args.fallback.unwrap_or_default().allows(&e) => {
+                            cache.get_stale(key)
+                        }
+                        _ => None,
+                    };
+                    let entry = fallback.unwrap_or_else(|| unwrap_error(Err(e)));
+                    note = Some(format!("cached from {}", cached_on(&entry)));
+                    cache_info = CacheInfo {
+                        stale: true,
+                        ..CacheInfo::hit(entry.written_at())
+                    };
+                    (entry.verse, provider_translation, false)
+                }
+            }
         }
+        (None, None, None) => (unwrap_error(client.random()), provider_translation, false),
+    };
+
+    let document = Document::new(&verse, &translation, is_votd, cache_info);
+    let output = if let Some(format) = args.format {
+        document.render(format)
+    } else if let Some(template) = &template {
+        let layout = text_layout(&args);
+        let text = TextLayout {
+            width: None,
+            ..layout
+        }
+        .lines(&verse)
+        .join("\n");
+        let mut output = String::new();
+        for line in layout.wrap_text(&template.render(&document, &text)) {
+            writeln!(output, "{}", line).expect("writing to a String can't fail");
+        }
+        output
     } else {
-        let verse = unwrap_error(fetch_verse(verse_requested.as_deref(), timeout));
-        (verse, verse_requested.is_none() && !args.no_cache)
+        let mut output = format_verse(&args, &verse, &translation, is_votd, note.as_deref());
+        if let Some((page, pages)) = page {
+            output.push_str(&page_footer(page, pages));
+        }
+        output
     };
+    show(&args, &output);
+
+    if let (Some(cache), Some((key, _))) = (cache.as_mut(), &key) {
+        if write_cache {
+            cache.insert(key, &verse);
+        }
+        unwrap_error(cache.save());
+    }
+    if refreshing {
+        refresh_in_background(&args, &translation);
+    }
+}
+
+/// Looks `reference` up, along with the translation of whatever answered:
+/// the store if it holds the passage, or otherwise the provider, reading
+/// from and writing to `cache` (if there is one) under the provider's
+/// translation.
+fn lookup_cached(
+    client: &VerseClient,
+    cache: Option<&mut VerseCache>,
+    reference: &Reference,
+) -> votd::Result<(Verse, String)> {
+    if let Some((verse, translation)) = client.lookup_stored(reference)? {
+        return Ok((verse, translation.to_owned()));
+    }
+    let translation = client.provider().translation().to_owned();
+    let cache = match cache {
+        Some(cache) => cache,
+        None => return Ok((client.provider().lookup(reference)?, translation)),
+    };
+    let key = CacheKey::passage(&translation, reference);
+    if let Some(entry) = cache.get(&key) {
+        return Ok((entry.verse, translation));
+    }
+    let verse = client.provider().lookup(reference)?;
+    cache.insert(&key, &verse);
+    cache.save()?;
+    Ok((verse, translation))
+}
+
+/// The date `entry` was cached for: the day it was the verse-of-the-day, or
+/// otherwise the (local) day it was written.
+fn cached_on(entry: &CacheEntry) -> NaiveDate {
+    entry
+        .date
+        .unwrap_or_else(|| DateTime::<Local>::from(entry.written_at()).date_naive())
+}
 
+/// Lays the passage out for the terminal, with `note` (if any) after the
+/// title.
+fn format_verse(
+    args: &VerseOpts,
+    verse: &Verse,
+    translation: &str,
+    votd: bool,
+    note: Option<&str>,
+) -> String {
+    let mut output = String::new();
     if !args.only_verse {
-        print!("{}", verse.title);
+        output.push_str(&verse.title());
         if args.show_translation {
-            print!(
-                " ({})",
-                if verse_requested.is_none() {
-                    "Verse of the Day - NET"
-                } else {
-                    "NET"
-                }
-            );
-        } else if verse_requested.is_none() {
-            print!(" (Verse of the Day)");
+            if votd {
+                write!(output, " (Verse of the Day - {})", translation)
+            } else {
+                write!(output, " ({})", translation)
+            }
+            .expect("writing to a String can't fail");
+        } else if votd {
+            output.push_str(" (Verse of the Day)");
+        }
+        if let Some(note) = note {
+            write!(output, " ({})", note).expect("writing to a String can't fail");
         }
-        println!();
+        output.push('\n');
+    }
+    for line in text_layout(args).lines(verse) {
+        writeln!(output, "{}", line).expect("writing to a String can't fail");
+    }
+    output
+}
+
+/// Picks out the page of `reference` asked for with `--page`, along with
+/// its number and how many pages there are. Whole chapters and books are
+/// always paged, and other passages only if `--page` or `--limit` is given;
+/// a passage that fits on one page is left as it is.
+fn select_page(args: &VerseOpts, reference: Reference) -> (Reference, Option<(usize, usize)>) {
+    if !reference.is_whole_chapters() && args.page.is_none() && args.limit.is_none() {
+        return (reference, None);
+    }
+    let mut pages = reference.pages(args.limit);
+    let count = pages.len();
+    let page = args.page.unwrap_or(1);
+    if page == 0 {
+        fail(sysexits::USAGE, "pages are numbered from 1");
+    }
+    if page > count {
+        let pages = if count == 1 { "page" } else { "pages" };
+        fail(
+            sysexits::USAGE,
+            &format!("{} only has {} {}", reference, count, pages),
+        );
     }
+    if count == 1 {
+        return (reference, None);
+    }
+    (pages.swap_remove(page - 1), Some((page, count)))
+}
+
+/// Says which page is shown, and how to get to the next one.
+fn page_footer(page: usize, pages: usize) -> String {
+    if page < pages {
+        format!(
+            "\nPage {} of {}; --page {} shows the next\n",
+            page,
+            pages,
+            page + 1
+        )
+    } else {
+        format!("\nPage {} of {}\n", page, pages)
+    }
+}
+
+/// Writes `output` to stdout, through `$PAGER` (or `less`) if stdout is a
+/// terminal it doesn't fit on.
+fn show(args: &VerseOpts, output: &str) {
+    let fits = terminal_size::terminal_size().map_or(true, |(_, terminal_size::Height(h))| {
+        output.lines().count() < h as usize
+    });
+    if fits || args.no_pager || page_output(output).is_err() {
+        print!("{}", output);
+    }
+}
+
+/// Shows `output` in the user's pager, failing if it can't be started.
+fn page_output(output: &str) -> std::io::Result<()> {
+    let pager = std::env::var("PAGER")
+        .ok()
+        .filter(|pager| !pager.trim().is_empty())
+        .unwrap_or_else(|| "less".to_owned());
+    let mut words = pager.split_whitespace();
+    let program = words.next().unwrap_or("less");
+    let mut command = std::process::Command::new(program);
+    command.args(words).stdin(Stdio::piped());
+    if std::env::var_os("LESS").is_none() {
+        // Keep colors, and don't page output that fits after all.
+        command.env("LESS", "FRX");
+    }
+    let mut child = command.spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // The pager may be quit before it's read everything.
+        let _ = stdin.write_all(output.as_bytes());
+    }
+    let _ = child.wait();
+    Ok(())
+}
+
+fn text_layout(args: &VerseOpts) -> TextLayout {
     let size = terminal_size::terminal_size()
         .map(|(terminal_size::Width(w), _)| w as usize)
         .filter(|_| !args.no_wrap);
-    if let Some(size) = size {
-        let wrapped = textwrap::wrap(&verse.text, size);
-        for line in wrapped {
-            println!("{}", line);
+    TextLayout {
+        verse_numbers: Some(args.number_style.unwrap_or_default())
+            .filter(|_| args.verse_numbers == Some(true)),
+        one_per_line: args.one_per_line,
+        width: size,
+        emphasis: args.emphasis,
+        headings: args.headings,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use votd::{VerseProvider, VerseText};
+
+    /// A provider that answers every lookup with the same verse.
+    struct MockProvider;
+
+    fn mock_verse() -> Verse {
+        Verse {
+            verses: vec![VerseText {
+                book: "Mock".to_owned(),
+                chapter: 1,
+                verse: 1,
+                text: "From the provider.".to_owned(),
+                styles: Vec::new(),
+                headings: Vec::new(),
+            }],
+        }
+    }
+
+    impl VerseProvider for MockProvider {
+        fn name(&self) -> &str {
+            "mock"
+        }
+
+        fn translation(&self) -> &str {
+            "MOCK"
+        }
+
+        fn lookup(&self, _reference: &Reference) -> votd::Result<Verse> {
+            Ok(mock_verse())
+        }
+
+        fn votd(&self) -> votd::Result<Verse> {
+            Ok(mock_verse())
+        }
+
+        fn random(&self) -> votd::Result<Verse> {
+            Ok(mock_verse())
         }
-    } else {
-        println!("{}", &verse.text);
     }
 
-    if write_cache && cache.is_some() {
-        let (mut cache_file, _) = cache.expect("Cache has to contain a value to reach this code");
-        rmp_serde::encode::write(&mut cache_file, &verse).unwrap();
+    #[test]
+    fn store_misses_are_cached_under_the_providers_translation() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("john.json");
+        std::fs::write(
+            &source,
+            r#"[{"book": "John", "chapter": 3, "verse": 16, "text": "From the store."}]"#,
+        )
+        .unwrap();
+        let root = dir.path().join("bibles");
+        BibleStore::import(&root, "TEST", &source, ImportFormat::Json).unwrap();
+        let store = BibleStore::open(&root, "TEST").unwrap().unwrap();
+        let client = VerseClient::with_provider(MockProvider).with_store(store);
+        let mut cache = VerseCache::open(&dir.path().join("cache"), 10).unwrap();
+
+        let stored: Reference = "John 3:16".parse().unwrap();
+        let (verse, translation) = lookup_cached(&client, Some(&mut cache), &stored).unwrap();
+        assert_eq!(verse.text(), "From the store.");
+        assert_eq!(translation, "TEST");
+        assert_eq!(cache.len(), 0);
+
+        let missing: Reference = "John 3:18".parse().unwrap();
+        let (verse, translation) = lookup_cached(&client, Some(&mut cache), &missing).unwrap();
+        assert_eq!(verse.text(), "From the provider.");
+        assert_eq!(translation, "MOCK");
+        let keys: Vec<CacheKey> = cache.entries().map(|(key, _)| key).collect();
+        assert_eq!(keys, [CacheKey::passage("MOCK", &missing)]);
+        assert!(cache.get(&CacheKey::passage("TEST", &missing)).is_none());
+
+        // The next lookup is answered from the cache, still as the provider's.
+        let (_, translation) = lookup_cached(&client, Some(&mut cache), &missing).unwrap();
+        assert_eq!(translation, "MOCK");
     }
 }
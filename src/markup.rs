@@ -0,0 +1,368 @@
+//! Normalization of verse text that contains HTML, as the NET Bible API's
+//! sometimes does: entities are decoded, tags are stripped (footnote markers
+//! along with their contents), and bold and italic text is remembered so it
+//! can be styled for the terminal.
+
+use serde_derive::{Deserialize, Serialize};
+
+/// A kind of emphasis from the source text.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextStyle {
+    Bold,
+    Italic,
+}
+
+/// Emphasis applied to part of a verse's text, by byte offsets.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StyleSpan {
+    pub start: usize,
+    pub end: usize,
+    pub style: TextStyle,
+}
+
+/// Plain text, with the emphasis that was marked up in it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Normalized {
+    pub text: String,
+    pub styles: Vec<StyleSpan>,
+}
+
+/// Elements whose contents are dropped entirely.
+const HIDDEN_ELEMENTS: &[&str] = &["sup", "script", "style", "head", "title"];
+
+/// Elements that never have a closing tag, even when written without `/>`.
+const VOID_ELEMENTS: &[&str] = &[
+    "br", "hr", "img", "input", "meta", "link", "wbr", "col", "area", "base", "source",
+];
+
+/// Elements that separate words, so are replaced by a space.
+const BREAKING_ELEMENTS: &[&str] = &["br", "p", "div", "li", "tr", "td", "h1", "h2", "h3", "h4"];
+
+fn named_entity(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "amp" => "&",
+        "lt" => "<",
+        "gt" => ">",
+        "quot" => "\"",
+        "apos" => "'",
+        "nbsp" | "ensp" | "emsp" | "thinsp" => " ",
+        "shy" | "zwj" | "zwnj" => "",
+        "ndash" => "–",
+        "mdash" => "—",
+        "lsquo" => "‘",
+        "rsquo" => "’",
+        "sbquo" => "‚",
+        "ldquo" => "“",
+        "rdquo" => "”",
+        "bdquo" => "„",
+        "laquo" => "«",
+        "raquo" => "»",
+        "hellip" => "…",
+        "middot" => "·",
+        "bull" => "•",
+        "dagger" => "†",
+        "Dagger" => "‡",
+        "sect" => "§",
+        "para" => "¶",
+        "deg" => "°",
+        "prime" => "′",
+        "Prime" => "″",
+        "times" => "×",
+        "divide" => "÷",
+        "frac12" => "½",
+        "frac14" => "¼",
+        "frac34" => "¾",
+        "copy" => "©",
+        "reg" => "®",
+        "trade" => "™",
+        "aacute" => "á",
+        "eacute" => "é",
+        "iacute" => "í",
+        "oacute" => "ó",
+        "uacute" => "ú",
+        "agrave" => "à",
+        "egrave" => "è",
+        "auml" => "ä",
+        "euml" => "ë",
+        "iuml" => "ï",
+        "ouml" => "ö",
+        "uuml" => "ü",
+        "ccedil" => "ç",
+        "ntilde" => "ñ",
+        _ => return None,
+    })
+}
+
+/// Decodes the entity at the start of `s` (just after the `&`), returning
+/// its text and length including the `;`.
+fn decode_entity(s: &str) -> Option<(String, usize)> {
+    let end = s.char_indices().take(12).find(|&(_, c)| c == ';')?.0;
+    let name = &s[..end];
+    let decoded = if let Some(number) = name.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+        char::from_u32(code)?.to_string()
+    } else {
+        named_entity(name)?.to_owned()
+    };
+    Some((decoded, end + 1))
+}
+
+struct Tag<'a> {
+    name: String,
+    closing: bool,
+    self_closing: bool,
+    attributes: &'a str,
+}
+
+fn parse_tag(inner: &str) -> Tag<'_> {
+    let closing = inner.starts_with('/');
+    let inner = inner.trim_start_matches('/');
+    let self_closing = inner.ends_with('/');
+    let inner = inner.trim_end_matches('/');
+    let name_end = inner
+        .find(|c: char| c.is_whitespace())
+        .unwrap_or(inner.len());
+    Tag {
+        name: inner[..name_end].to_ascii_lowercase(),
+        closing,
+        self_closing,
+        attributes: &inner[name_end..],
+    }
+}
+
+fn is_note(attributes: &str) -> bool {
+    let attributes = attributes.to_ascii_lowercase();
+    attributes.contains("footnote") || attributes.contains("class=\"note")
+}
+
+struct Builder {
+    out: Normalized,
+    pending_space: bool,
+}
+
+impl Builder {
+    fn push_str(&mut self, s: &str) {
+        for c in s.chars() {
+            if c.is_whitespace() {
+                self.pending_space = true;
+            } else {
+                if self.pending_space && !self.out.text.is_empty() {
+                    self.out.text.push(' ');
+                }
+                self.pending_space = false;
+                self.out.text.push(c);
+            }
+        }
+    }
+
+    /// The offset the next character will be written at.
+    fn position(&self) -> usize {
+        self.out.text.len() + usize::from(self.pending_space && !self.out.text.is_empty())
+    }
+}
+
+/// Normalizes text that may contain HTML tags and entities.
+pub fn normalize(raw: &str) -> Normalized {
+    let mut builder = Builder {
+        out: Normalized::default(),
+        pending_space: false,
+    };
+    // The elements open inside the outermost hidden one (so a nested tag of
+    // the same name doesn't end it early), and the emphasis currently open
+    // (with where it started).
+    let mut hidden: Vec<String> = Vec::new();
+    let mut open: Vec<(String, TextStyle, usize)> = Vec::new();
+
+    let mut rest = raw;
+    while let Some(c) = rest.chars().next() {
+        if c == '<' {
+            if let Some(end) = rest.find('>') {
+                let tag = parse_tag(&rest[1..end]);
+                rest = &rest[end + 1..];
+                let style = match tag.name.as_str() {
+                    "b" | "strong" => Some(TextStyle::Bold),
+                    "i" | "em" | "cite" => Some(TextStyle::Italic),
+                    _ => None,
+                };
+                let hides = HIDDEN_ELEMENTS.contains(&tag.name.as_str()) || is_note(tag.attributes);
+                if tag.closing {
+                    if let Some(i) = hidden.iter().rposition(|name| *name == tag.name) {
+                        hidden.truncate(i);
+                    } else if let Some(i) = open.iter().rposition(|(name, _, _)| *name == tag.name)
+                    {
+                        let (_, style, start) = open.remove(i);
+                        let end = builder.out.text.len();
+                        if end > start {
+                            builder.out.styles.push(StyleSpan { start, end, style });
+                        }
+                    }
+                } else if tag.self_closing || VOID_ELEMENTS.contains(&tag.name.as_str()) {
+                    // Nothing to hide or style.
+                } else if hides || !hidden.is_empty() {
+                    hidden.push(tag.name.clone());
+                } else if let Some(style) = style {
+                    open.push((tag.name.clone(), style, builder.position()));
+                }
+                if BREAKING_ELEMENTS.contains(&tag.name.as_str()) {
+                    builder.pending_space = true;
+                }
+                continue;
+            }
+        }
+        let (text, len) = if c == '&' {
+            decode_entity(&rest[1..])
+                .map(|(text, len)| (text, len + 1))
+                .unwrap_or_else(|| ("&".to_owned(), 1))
+        } else {
+            (c.to_string(), c.len_utf8())
+        };
+        if hidden.is_empty() {
+            builder.push_str(&text);
+        }
+        rest = &rest[len..];
+    }
+
+    // Close any emphasis that was never closed.
+    let end = builder.out.text.len();
+    for (_, style, start) in open {
+        if end > start {
+            builder.out.styles.push(StyleSpan { start, end, style });
+        }
+    }
+    builder.out.styles.sort_by_key(|span| span.start);
+    builder.out
+}
+
+/// Applies `styles` to `text` as ANSI escape sequences.
+pub fn to_ansi(text: &str, styles: &[StyleSpan]) -> String {
+    let mut events: Vec<(usize, bool, TextStyle)> = styles
+        .iter()
+        .filter(|span| {
+            span.end <= text.len()
+                && text.is_char_boundary(span.start)
+                && text.is_char_boundary(span.end)
+        })
+        .flat_map(|span| {
+            [
+                (span.start, true, span.style),
+                (span.end, false, span.style),
+            ]
+        })
+        .collect();
+    // Close styles before opening others at the same offset.
+    events.sort_by_key(|(offset, opening, _)| (*offset, *opening));
+
+    let mut out = String::with_capacity(text.len());
+    let mut written = 0;
+    let mut depth = [0usize; 2];
+    for (offset, opening, style) in events {
+        out.push_str(&text[written..offset]);
+        written = offset;
+        let (index, on, off) = match style {
+            TextStyle::Bold => (0, "\x1b[1m", "\x1b[22m"),
+            TextStyle::Italic => (1, "\x1b[3m", "\x1b[23m"),
+        };
+        if opening {
+            if depth[index] == 0 {
+                out.push_str(on);
+            }
+            depth[index] += 1;
+        } else {
+            depth[index] = depth[index].saturating_sub(1);
+            if depth[index] == 0 {
+                out.push_str(off);
+            }
+        }
+    }
+    out.push_str(&text[written..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(start: usize, end: usize, style: TextStyle) -> StyleSpan {
+        StyleSpan { start, end, style }
+    }
+
+    /// Verse text in the shapes the NET Bible API sends it, with the text
+    /// and emphasis it should normalize to.
+    #[test]
+    fn normalizes_api_text() {
+        let snapshots: &[(&str, &str, &[StyleSpan])] = &[
+            (
+                "For this is the way God loved the world: He gave his one and only Son, so that everyone who believes in him will not perish but have eternal life.",
+                "For this is the way God loved the world: He gave his one and only Son, so that everyone who believes in him will not perish but have eternal life.",
+                &[],
+            ),
+            (
+                "The <b>Lord</b> is my shepherd, I lack nothing.",
+                "The Lord is my shepherd, I lack nothing.",
+                &[span(4, 8, TextStyle::Bold)],
+            ),
+            (
+                "In the beginning<sup class=\"footnote\">1</sup> God<sup>2</sup> created the heavens and the earth.",
+                "In the beginning God created the heavens and the earth.",
+                &[],
+            ),
+            (
+                "&#8220;Don&#8217;t be afraid,&#8221; he said &ndash; <i>&ldquo;only believe.&rdquo;</i>",
+                "“Don’t be afraid,” he said – “only believe.”",
+                &[span(37, 56, TextStyle::Italic)],
+            ),
+            (
+                "Blessed is the one<br>who does not follow<br/>the advice of the wicked,",
+                "Blessed is the one who does not follow the advice of the wicked,",
+                &[],
+            ),
+            (
+                "<p class=\"bodytext\">Jesus wept.</p>",
+                "Jesus wept.",
+                &[],
+            ),
+            (
+                "grace <span class=\"note\" id=\"n1\">tn <i>Grk</i> “favor”</span>and peace",
+                "grace and peace",
+                &[],
+            ),
+        ];
+        for (raw, text, styles) in snapshots {
+            let normalized = normalize(raw);
+            assert_eq!(normalized.text, *text, "text of {:?}", raw);
+            assert_eq!(normalized.styles, *styles, "styles of {:?}", raw);
+        }
+    }
+
+    #[test]
+    fn hides_footnotes_containing_tags_of_the_same_name() {
+        let normalized = normalize("<span class=\"footnote\">a <span>b</span> c</span> world");
+        assert_eq!(normalized.text, "world");
+        let normalized = normalize("hello<sup>1 <sup>a</sup> note</sup> world");
+        assert_eq!(normalized.text, "hello world");
+    }
+
+    #[test]
+    fn void_elements_in_footnotes_dont_hide_the_rest() {
+        let normalized = normalize("a<span class=\"footnote\">b<br>c</span> d");
+        assert_eq!(normalized.text, "a d");
+    }
+
+    #[test]
+    fn unclosed_tags_in_footnotes_end_with_it() {
+        let normalized = normalize("a<span class=\"footnote\">b <i>c</span> d");
+        assert_eq!(normalized.text, "a d");
+    }
+
+    #[test]
+    fn styles_become_ansi_escapes() {
+        let normalized = normalize("The <b>Lord</b> is <i>my</i> shepherd");
+        assert_eq!(
+            to_ansi(&normalized.text, &normalized.styles),
+            "The \x1b[1mLord\x1b[22m is \x1b[3mmy\x1b[23m shepherd"
+        );
+    }
+}
@@ -0,0 +1,337 @@
+//! How providers make HTTP requests: timeouts, retries, proxies, and TLS.
+
+use crate::error::{Result, VotdError};
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+#[cfg(not(any(feature = "native-tls", feature = "rustls")))]
+compile_error!("votd needs a TLS implementation; enable the `native-tls` or `rustls` feature");
+
+/// The TLS implementation requests are made with. Which are available
+/// depends on the `native-tls` and `rustls` cargo features.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tls {
+    /// The platform's TLS library (e.g. OpenSSL, Schannel, or Secure
+    /// Transport).
+    Native,
+    /// rustls, with Mozilla's root certificates.
+    Rustls,
+}
+
+impl Default for Tls {
+    fn default() -> Self {
+        if cfg!(feature = "native-tls") {
+            Tls::Native
+        } else {
+            Tls::Rustls
+        }
+    }
+}
+
+impl fmt::Display for Tls {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Tls::Native => f.write_str("native"),
+            Tls::Rustls => f.write_str("rustls"),
+        }
+    }
+}
+
+impl FromStr for Tls {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "native" | "native-tls" => Ok(Tls::Native),
+            "rustls" => Ok(Tls::Rustls),
+            _ => Err(format!("unknown TLS {:?}; expected native or rustls", s)),
+        }
+    }
+}
+
+/// Options for the HTTP client providers make requests with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetworkOptions {
+    /// How long each request may take in total, from connecting to reading
+    /// the end of the response. It's not a read timeout: it isn't reset as
+    /// data arrives, so a response that trickles in can still time out
+    /// (reqwest's blocking client has no separate read timeout). Each retry
+    /// gets a new `timeout`.
+    pub timeout: Duration,
+    /// How long to wait to connect to the server.
+    pub connect_timeout: Duration,
+    /// How many times to retry a request that failed in a way that might
+    /// not happen again (see [`VotdError::is_transient`]).
+    pub retries: u32,
+    /// How long to wait before the first retry; each one after waits about
+    /// twice as long as the last.
+    pub retry_delay: Duration,
+    /// The proxy to send every request through, or "none" to not use one.
+    /// By default, the proxies given by the `HTTP_PROXY`, `HTTPS_PROXY`,
+    /// and `NO_PROXY` environment variables are used.
+    pub proxy: Option<String>,
+    /// A PEM file of extra root certificates to trust.
+    pub ca_bundle: Option<PathBuf>,
+    /// The TLS implementation to use.
+    pub tls: Tls,
+}
+
+impl Default for NetworkOptions {
+    fn default() -> Self {
+        NetworkOptions {
+            timeout: Duration::from_secs(2),
+            connect_timeout: Duration::from_secs(2),
+            retries: 0,
+            retry_delay: Duration::from_millis(250),
+            proxy: None,
+            ca_bundle: None,
+            tls: Tls::default(),
+        }
+    }
+}
+
+impl NetworkOptions {
+    /// The default options, with requests giving up after `timeout`.
+    pub fn with_timeout(timeout: Duration) -> Self {
+        NetworkOptions {
+            timeout,
+            connect_timeout: timeout,
+            ..NetworkOptions::default()
+        }
+    }
+
+    /// Builds an HTTP client using these options.
+    pub fn client(&self) -> Result<reqwest::blocking::Client> {
+        let mut builder = reqwest::blocking::Client::builder()
+            .timeout(self.timeout)
+            .connect_timeout(self.connect_timeout);
+        builder = match self.tls {
+            #[cfg(feature = "native-tls")]
+            Tls::Native => builder.use_native_tls(),
+            #[cfg(feature = "rustls")]
+            Tls::Rustls => builder.use_rustls_tls(),
+            #[allow(unreachable_patterns)]
+            tls => {
+                return Err(VotdError::Config(format!(
+                    "votd was built without {} TLS support",
+                    tls
+                )))
+            }
+        };
+        match self.proxy.as_deref() {
+            Some(proxy) if proxy.eq_ignore_ascii_case("none") => builder = builder.no_proxy(),
+            Some(proxy) => {
+                let proxy = reqwest::Proxy::all(proxy)
+                    .map_err(|e| VotdError::Config(format!("invalid proxy {:?}: {}", proxy, e)))?;
+                builder = builder.proxy(proxy);
+            }
+            None => {}
+        }
+        if let Some(path) = &self.ca_bundle {
+            let invalid = |e: &dyn fmt::Display| {
+                VotdError::Config(format!("CA bundle {}: {}", path.display(), e))
+            };
+            let pem = std::fs::read(path).map_err(|e| invalid(&e))?;
+            let certificates =
+                reqwest::Certificate::from_pem_bundle(&pem).map_err(|e| invalid(&e))?;
+            if certificates.is_empty() {
+                return Err(invalid(&"no certificates found"));
+            }
+            for certificate in certificates {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+        Ok(builder.build()?)
+    }
+
+    /// Calls `request` until it succeeds, it fails in a way retrying won't
+    /// fix, or it has been retried [`retries`](NetworkOptions::retries)
+    /// times.
+    pub fn retry<T>(&self, mut request: impl FnMut() -> Result<T>) -> Result<T> {
+        let mut attempt = 0;
+        loop {
+            match request() {
+                Err(e) if attempt < self.retries && e.is_transient() => {
+                    std::thread::sleep(self.backoff(attempt));
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// How long to wait before retrying for the `attempt`th time (from 0):
+    /// between half and all of `retry_delay * 2^attempt`, chosen at random so
+    /// that several clients retrying at once spread out.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let delay = self
+            .retry_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            / 2;
+        let random = RandomState::new().build_hasher().finish();
+        delay + delay.mul_f64(random as f64 / u64::MAX as f64)
+    }
+}
+
+/// A stand-in HTTP server for tests.
+#[cfg(test)]
+pub(crate) mod mock {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+
+    /// A server on a free local port that gives each of `responses` (a
+    /// status code and body), in order, to one connection each, then
+    /// stops. Returns its address and a handle that gives the request line
+    /// of every request it served.
+    pub(crate) fn serve(responses: Vec<(u16, &'static str)>) -> (String, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = std::thread::spawn(move || {
+            let mut requests = Vec::new();
+            for (status, body) in responses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                requests.push(line.trim_end().to_owned());
+                // Skip the headers; none of the requests have a body.
+                while line != "\r\n" && !line.is_empty() {
+                    line.clear();
+                    reader.read_line(&mut line).unwrap();
+                }
+                write!(
+                    reader.get_mut(),
+                    "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\n\
+                     Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                )
+                .unwrap();
+            }
+            requests
+        });
+        (address, server)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_with_jitter() {
+        let network = NetworkOptions {
+            retry_delay: Duration::from_millis(100),
+            ..NetworkOptions::default()
+        };
+        for attempt in 0..4 {
+            let most = Duration::from_millis(100 * 2u64.pow(attempt));
+            for _ in 0..20 {
+                let backoff = network.backoff(attempt);
+                assert!(backoff >= most / 2 && backoff <= most, "{:?}", backoff);
+            }
+        }
+        // Far enough along, the delay stops growing rather than overflowing.
+        let most = Duration::from_millis(100) * u32::MAX;
+        assert!(network.backoff(u32::MAX) >= most / 2);
+        assert!(network.backoff(u32::MAX) <= most);
+    }
+
+    #[test]
+    fn retries_transient_errors_until_they_run_out() {
+        let network = NetworkOptions {
+            retries: 2,
+            retry_delay: Duration::from_millis(1),
+            ..NetworkOptions::default()
+        };
+        let unavailable = || VotdError::Status(reqwest::StatusCode::SERVICE_UNAVAILABLE);
+        let mut attempts = 0;
+        let result: Result<()> = network.retry(|| {
+            attempts += 1;
+            Err(unavailable())
+        });
+        assert!(matches!(result, Err(VotdError::Status(_))));
+        assert_eq!(attempts, 3);
+
+        let mut attempts = 0;
+        let result = network.retry(|| {
+            attempts += 1;
+            if attempts < 3 {
+                Err(unavailable())
+            } else {
+                Ok(attempts)
+            }
+        });
+        assert_eq!(result.unwrap(), 3);
+
+        let mut attempts = 0;
+        let result: Result<()> = network.retry(|| {
+            attempts += 1;
+            Err(VotdError::NotFound("John 3:99".to_owned()))
+        });
+        assert!(matches!(result, Err(VotdError::NotFound(_))));
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn requests_go_through_the_proxy() {
+        let (proxy, server) = mock::serve(vec![(200, "[]")]);
+        let network = NetworkOptions {
+            proxy: Some(format!("http://{}", proxy)),
+            ..NetworkOptions::default()
+        };
+        let response = network
+            .client()
+            .unwrap()
+            .get("http://votd.invalid/api/?passage=votd")
+            .send()
+            .unwrap();
+        assert_eq!(response.text().unwrap(), "[]");
+        // A proxy is sent the whole URL, not just the path.
+        assert_eq!(
+            server.join().unwrap(),
+            ["GET http://votd.invalid/api/?passage=votd HTTP/1.1"]
+        );
+    }
+
+    #[test]
+    fn connecting_defaults_to_the_request_timeout() {
+        let network = NetworkOptions::with_timeout(Duration::from_secs(5));
+        assert_eq!(network.timeout, Duration::from_secs(5));
+        assert_eq!(network.connect_timeout, Duration::from_secs(5));
+    }
+
+    #[test]
+    fn the_timeout_covers_a_server_that_connects_but_never_answers() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let network = NetworkOptions {
+            timeout: Duration::from_millis(200),
+            connect_timeout: Duration::from_secs(5),
+            ..NetworkOptions::default()
+        };
+        let error = network
+            .client()
+            .unwrap()
+            .get(format!("http://{}/", address))
+            .send()
+            .unwrap_err();
+        assert!(matches!(VotdError::from(error), VotdError::Timeout));
+        drop(listener);
+    }
+
+    #[test]
+    fn invalid_proxies_are_config_errors() {
+        let network = NetworkOptions {
+            proxy: Some("http://[::1".to_owned()),
+            ..NetworkOptions::default()
+        };
+        assert!(matches!(network.client(), Err(VotdError::Config(_))));
+    }
+}
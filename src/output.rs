@@ -0,0 +1,361 @@
+//! Machine-readable and document output of a looked-up passage, for scripts,
+//! bots, and docs generators that shouldn't have to scrape the terminal
+//! output.
+
+use crate::verse::Verse;
+use serde_derive::Serialize;
+use std::fmt::{self, Write};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The version of the document layout. It only changes when a field is
+/// removed or its meaning changes; new fields may be added without a bump.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// A format to write a passage in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Json,
+    Yaml,
+    Markdown,
+    /// The title and text on a line each, without wrapping or styling.
+    Plain,
+    Html,
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            OutputFormat::Json => "json",
+            OutputFormat::Yaml => "yaml",
+            OutputFormat::Markdown => "markdown",
+            OutputFormat::Plain => "plain",
+            OutputFormat::Html => "html",
+        })
+    }
+}
+
+/// The names accepted when parsing an [`OutputFormat`].
+pub const OUTPUT_FORMAT_NAMES: &[&str] = &["json", "yaml", "markdown", "plain", "html"];
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(OutputFormat::Json),
+            "yaml" | "yml" => Ok(OutputFormat::Yaml),
+            "markdown" | "md" => Ok(OutputFormat::Markdown),
+            "plain" | "text" | "txt" => Ok(OutputFormat::Plain),
+            "html" => Ok(OutputFormat::Html),
+            _ => Err(format!(
+                "unknown format {:?}; expected one of: {}",
+                s,
+                OUTPUT_FORMAT_NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+/// Where a passage came from, as far as the verse-of-the-day cache goes.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CacheInfo {
+    /// Whether the passage was read from the cache.
+    pub hit: bool,
+    /// When the cached passage was written, in seconds since the Unix epoch.
+    pub written_at: Option<u64>,
+    /// Whether the cached passage is out of date, and was shown because a
+    /// current one is being fetched in the background or couldn't be.
+    pub stale: bool,
+}
+
+impl CacheInfo {
+    /// A passage read from a cache written at `written_at`.
+    pub fn hit(written_at: SystemTime) -> Self {
+        CacheInfo {
+            hit: true,
+            written_at: written_at
+                .duration_since(UNIX_EPOCH)
+                .ok()
+                .map(|since| since.as_secs()),
+            stale: false,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DocumentVerse<'a> {
+    pub(crate) book: &'a str,
+    pub(crate) chapter: u32,
+    pub(crate) verse: u32,
+    pub(crate) text: &'a str,
+    pub(crate) headings: &'a [String],
+}
+
+/// A passage along with what's known about it, as written by
+/// [`Document::render`].
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Document<'a> {
+    format_version: u32,
+    pub(crate) reference: String,
+    pub(crate) translation: &'a str,
+    pub(crate) votd: bool,
+    pub(crate) text: String,
+    pub(crate) verses: Vec<DocumentVerse<'a>>,
+    pub(crate) cache: CacheInfo,
+}
+
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '*' | '_' | '`' | '[' | ']' | '<' | '>' | '#') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+impl<'a> Document<'a> {
+    /// Describes `verse`, in `translation`, which is the verse-of-the-day if
+    /// `votd` is set.
+    pub fn new(verse: &'a Verse, translation: &'a str, votd: bool, cache: CacheInfo) -> Self {
+        Document {
+            format_version: FORMAT_VERSION,
+            reference: verse.title(),
+            translation,
+            votd,
+            text: verse.text(),
+            verses: verse
+                .verses
+                .iter()
+                .map(|verse| DocumentVerse {
+                    book: &verse.book,
+                    chapter: verse.chapter,
+                    verse: verse.verse,
+                    text: verse.text.trim(),
+                    headings: &verse.headings,
+                })
+                .collect(),
+            cache,
+        }
+    }
+
+    fn heading(&self) -> String {
+        if self.votd {
+            format!(
+                "{} (Verse of the Day - {})",
+                self.reference, self.translation
+            )
+        } else {
+            format!("{} ({})", self.reference, self.translation)
+        }
+    }
+
+    /// Writes the document in `format`, ending with a newline.
+    pub fn render(&self, format: OutputFormat) -> String {
+        let mut out = match format {
+            OutputFormat::Json => {
+                serde_json::to_string_pretty(self).expect("documents can always be serialized")
+            }
+            OutputFormat::Yaml => {
+                serde_yaml::to_string(self).expect("documents can always be serialized")
+            }
+            OutputFormat::Markdown => format!(
+                "> {}\n>\n> — **{}**",
+                escape_markdown(&self.text),
+                escape_markdown(&self.heading())
+            ),
+            OutputFormat::Plain => format!("{}\n{}", self.heading(), self.text),
+            OutputFormat::Html => {
+                let mut html = String::from("<blockquote class=\"votd\">\n");
+                let mut in_paragraph = false;
+                for verse in &self.verses {
+                    // Each heading starts a new paragraph.
+                    if !verse.headings.is_empty() && in_paragraph {
+                        html.push_str("</p>\n");
+                        in_paragraph = false;
+                    }
+                    for heading in verse.headings {
+                        writeln!(html, "<h3 class=\"heading\">{}</h3>", escape_html(heading))
+                            .expect("writing to a String can't fail");
+                    }
+                    html.push_str(if in_paragraph { " " } else { "<p>" });
+                    in_paragraph = true;
+                    write!(
+                        html,
+                        "<span class=\"verse\" data-book=\"{}\" data-chapter=\"{}\" data-verse=\"{}\">{}</span>",
+                        escape_html(verse.book),
+                        verse.chapter,
+                        verse.verse,
+                        escape_html(verse.text)
+                    )
+                    .expect("writing to a String can't fail");
+                }
+                write!(
+                    html,
+                    "{}\n<cite>{}</cite>\n</blockquote>",
+                    if in_paragraph { "</p>" } else { "" },
+                    escape_html(&self.heading())
+                )
+                .expect("writing to a String can't fail");
+                html
+            }
+        };
+        if !out.ends_with('\n') {
+            out.push('\n');
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::verse::VerseText;
+
+    fn verse(verse: u32, text: &str, headings: &[&str]) -> VerseText {
+        VerseText {
+            book: "John".to_owned(),
+            chapter: 3,
+            verse,
+            text: text.to_owned(),
+            styles: Vec::new(),
+            headings: headings.iter().map(|&heading| heading.to_owned()).collect(),
+        }
+    }
+
+    fn passage() -> Verse {
+        Verse {
+            verses: vec![
+                verse(16, "For God so loved the world", &["God's Love"]),
+                verse(17, "that he sent his Son", &[]),
+            ],
+        }
+    }
+
+    fn cache() -> CacheInfo {
+        CacheInfo {
+            hit: true,
+            written_at: Some(1_792_300_000),
+            stale: false,
+        }
+    }
+
+    #[test]
+    fn json_is_versioned() {
+        let passage = passage();
+        let json = Document::new(&passage, "NET", true, cache()).render(OutputFormat::Json);
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "format_version": FORMAT_VERSION,
+                "reference": "John 3:16-17",
+                "translation": "NET",
+                "votd": true,
+                "text": "For God so loved the world that he sent his Son",
+                "verses": [
+                    {
+                        "book": "John",
+                        "chapter": 3,
+                        "verse": 16,
+                        "text": "For God so loved the world",
+                        "headings": ["God's Love"],
+                    },
+                    {
+                        "book": "John",
+                        "chapter": 3,
+                        "verse": 17,
+                        "text": "that he sent his Son",
+                        "headings": [],
+                    },
+                ],
+                "cache": {"hit": true, "written_at": 1_792_300_000, "stale": false},
+            })
+        );
+        assert!(json.ends_with("}\n"));
+    }
+
+    #[test]
+    fn yaml_has_the_same_shape_as_json() {
+        let passage = passage();
+        let document = Document::new(&passage, "NET", false, CacheInfo::default());
+        let yaml: serde_json::Value =
+            serde_yaml::from_str(&document.render(OutputFormat::Yaml)).unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&document.render(OutputFormat::Json)).unwrap();
+        assert_eq!(yaml, json);
+        assert_eq!(yaml["format_version"], FORMAT_VERSION);
+        assert_eq!(yaml["cache"]["written_at"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn html_is_escaped() {
+        let passage = Verse {
+            verses: vec![
+                verse(1, "Fish & \"loaves\" <b>", &["<Heading>"]),
+                verse(2, "and more", &[]),
+            ],
+        };
+        let html =
+            Document::new(&passage, "NET", false, CacheInfo::default()).render(OutputFormat::Html);
+        assert_eq!(
+            html,
+            "<blockquote class=\"votd\">\n\
+             <h3 class=\"heading\">&lt;Heading&gt;</h3>\n\
+             <p><span class=\"verse\" data-book=\"John\" data-chapter=\"3\" data-verse=\"1\">\
+             Fish &amp; &quot;loaves&quot; &lt;b&gt;</span> \
+             <span class=\"verse\" data-book=\"John\" data-chapter=\"3\" data-verse=\"2\">\
+             and more</span></p>\n\
+             <cite>John 3:1-2 (NET)</cite>\n\
+             </blockquote>\n"
+        );
+    }
+
+    #[test]
+    fn markdown_and_plain_text() {
+        let passage = Verse {
+            verses: vec![verse(16, "For *God* so loved [the world]", &[])],
+        };
+        let document = Document::new(&passage, "NET", true, CacheInfo::default());
+        assert_eq!(
+            document.render(OutputFormat::Markdown),
+            "> For \\*God\\* so loved \\[the world\\]\n>\n\
+             > — **John 3:16 (Verse of the Day - NET)**\n"
+        );
+        assert_eq!(
+            document.render(OutputFormat::Plain),
+            "John 3:16 (Verse of the Day - NET)\nFor *God* so loved [the world]\n"
+        );
+    }
+
+    #[test]
+    fn parses_format_names() {
+        for format in [
+            OutputFormat::Json,
+            OutputFormat::Yaml,
+            OutputFormat::Markdown,
+            OutputFormat::Plain,
+            OutputFormat::Html,
+        ] {
+            assert_eq!(format.to_string().parse(), Ok(format));
+        }
+        assert_eq!("YML".parse(), Ok(OutputFormat::Yaml));
+        assert!("xml".parse::<OutputFormat>().is_err());
+    }
+}
@@ -0,0 +1,56 @@
+//! Sources that verses can be looked up from.
+
+mod net;
+
+pub use net::NetBibleProvider;
+
+use crate::error::{Result, VotdError};
+use crate::network::NetworkOptions;
+use crate::reference::Reference;
+use crate::verse::Verse;
+use chrono::NaiveDate;
+use chrono_tz::Tz;
+
+/// The names accepted by [`by_name`].
+pub const PROVIDER_NAMES: &[&str] = &["net"];
+
+/// A source of Bible verses.
+pub trait VerseProvider: Send + Sync {
+    /// The short name used to select this provider, e.g. "net".
+    fn name(&self) -> &str;
+
+    /// The abbreviation of the translation this provider returns, e.g. "NET".
+    fn translation(&self) -> &str;
+
+    /// Looks up the passages in `reference`.
+    fn lookup(&self, reference: &Reference) -> Result<Verse>;
+
+    /// Retrieves the current verse-of-the-day.
+    fn votd(&self) -> Result<Verse>;
+
+    /// Retrieves a random verse.
+    fn random(&self) -> Result<Verse>;
+
+    /// Retrieves the verse-of-the-day for `date` (in [`timezone`]), or
+    /// `None` if the provider can only give today's.
+    ///
+    /// [`timezone`]: VerseProvider::timezone
+    fn votd_on(&self, _date: NaiveDate) -> Result<Option<Verse>> {
+        Ok(None)
+    }
+
+    /// The timezone in which the provider's verse-of-the-day changes at
+    /// midnight.
+    fn timezone(&self) -> Tz {
+        chrono_tz::UTC
+    }
+}
+
+/// Creates the provider called `name` (see [`PROVIDER_NAMES`]), which makes
+/// requests using `network`.
+pub fn by_name(name: &str, network: &NetworkOptions) -> Result<Box<dyn VerseProvider>> {
+    match name.to_ascii_lowercase().as_str() {
+        "net" => Ok(Box::new(NetBibleProvider::with_network(network)?)),
+        _ => Err(VotdError::UnknownProvider(name.to_owned())),
+    }
+}
@@ -0,0 +1,411 @@
+use crate::error::{Result, VotdError};
+use crate::markup;
+use crate::network::NetworkOptions;
+use crate::provider::VerseProvider;
+use crate::reference::Reference;
+use crate::verse::{Verse, VerseText};
+use chrono_tz::Tz;
+use const_format::concatcp;
+use serde_derive::Deserialize;
+use std::fmt;
+use std::time::Duration;
+
+const VERSE_URL: &str = "https://labs.bible.org/api/?type=json";
+const URL_PARSE_ERROR: &str = concatcp!(VERSE_URL, " should be a valid URL");
+const USER_AGENT: &str = "Mozilla/5.0 Gecko/20100101 Firefox/130.0";
+
+/// A chapter or verse number, which the API usually sends as a string but
+/// sometimes as a number.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum ApiNumber {
+    Number(u64),
+    Text(String),
+}
+
+impl ApiNumber {
+    /// The number, if it is one.
+    fn value(&self) -> Option<u32> {
+        match self {
+            ApiNumber::Number(n) => u32::try_from(*n).ok(),
+            ApiNumber::Text(s) => s.trim().parse().ok(),
+        }
+    }
+}
+
+impl fmt::Display for ApiNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiNumber::Number(n) => write!(f, "{}", n),
+            ApiNumber::Text(s) => write!(f, "{:?}", s),
+        }
+    }
+}
+
+/// One or many of something; the API sends single headings as a string
+/// rather than a list.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum OneOrMany<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+impl<T> OneOrMany<T> {
+    fn into_vec(self) -> Vec<T> {
+        match self {
+            OneOrMany::One(one) => vec![one],
+            OneOrMany::Many(many) => many,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct ApiVerse {
+    bookname: String,
+    chapter: ApiNumber,
+    verse: ApiNumber,
+    #[serde(default)]
+    text: String,
+    /// The heading of the section the verse starts, if any.
+    #[serde(default)]
+    title: Option<OneOrMany<String>>,
+    #[serde(default)]
+    titles: Option<OneOrMany<String>>,
+}
+
+/// Decodes the API's response to a request for `passage`.
+fn decode(body: &str, passage: &str) -> Result<Verse> {
+    let body = body.trim_start();
+    if body.starts_with('<') {
+        return Err(VotdError::MalformedResponse(
+            "received a web page instead of verses; the API may be down".to_owned(),
+        ));
+    }
+    if body.is_empty() {
+        return Err(VotdError::NotFound(passage.to_owned()));
+    }
+    let malformed = |e: serde_json::Error| VotdError::MalformedResponse(e.to_string());
+    let response: serde_json::Value = serde_json::from_str(body).map_err(malformed)?;
+    let response: Vec<ApiVerse> = match response {
+        serde_json::Value::Array(_) => serde_json::from_value(response).map_err(malformed)?,
+        _ => vec![serde_json::from_value(response).map_err(malformed)?],
+    };
+    let mut verses = Vec::new();
+    let mut headings = Vec::new();
+    for verse in response {
+        for heading in [verse.title, verse.titles]
+            .into_iter()
+            .flatten()
+            .flat_map(OneOrMany::into_vec)
+        {
+            let heading = markup::normalize(&heading).text;
+            if !heading.trim().is_empty() {
+                headings.push(heading);
+            }
+        }
+        // The text sometimes contains HTML tags and entities.
+        let normalized = markup::normalize(&verse.text);
+        let chapter = verse.chapter.value().ok_or_else(|| {
+            VotdError::MalformedResponse(format!(
+                "chapter {} is not a valid integer",
+                verse.chapter
+            ))
+        })?;
+        let number = match verse.verse.value() {
+            Some(number) => number,
+            // Psalms' superscriptions (e.g. "For the music director") come
+            // without a verse number; keep them as a heading of the first
+            // verse.
+            None => {
+                if !normalized.text.trim().is_empty() {
+                    headings.push(normalized.text);
+                }
+                continue;
+            }
+        };
+        verses.push(VerseText {
+            book: verse.bookname,
+            chapter,
+            verse: number,
+            text: normalized.text,
+            styles: normalized.styles,
+            headings: std::mem::take(&mut headings),
+        });
+    }
+    if verses.is_empty() {
+        return Err(VotdError::NotFound(passage.to_owned()));
+    }
+    Ok(Verse { verses })
+}
+
+/// Looks up verses from the NET Bible API at labs.bible.org.
+#[derive(Debug, Clone)]
+pub struct NetBibleProvider {
+    client: reqwest::blocking::Client,
+    network: NetworkOptions,
+    url: reqwest::Url,
+}
+
+impl NetBibleProvider {
+    /// Creates a provider whose requests give up after `timeout`.
+    pub fn new(timeout: Duration) -> Result<Self> {
+        NetBibleProvider::with_network(&NetworkOptions::with_timeout(timeout))
+    }
+
+    /// Creates a provider that makes requests using `network`.
+    pub fn with_network(network: &NetworkOptions) -> Result<Self> {
+        Ok(NetBibleProvider {
+            client: network.client()?,
+            network: network.clone(),
+            url: reqwest::Url::parse(VERSE_URL).expect(URL_PARSE_ERROR),
+        })
+    }
+
+    fn fetch(&self, passage: &str) -> Result<Verse> {
+        let mut url = self.url.clone();
+        url.query_pairs_mut().append_pair("passage", passage);
+        // The API returns status code 400 and a blank page when given an invalid
+        // verse to look-up, so error statuses are returned early instead of
+        // parsing the page as JSON, and 400 and 404 are reported as the
+        // passage not being found (which isn't retried).
+        let body = self
+            .network
+            .retry(|| {
+                Ok(self
+                    .client
+                    .get(url.clone())
+                    .header(reqwest::header::USER_AGENT, USER_AGENT)
+                    .send()?
+                    .error_for_status()?
+                    .text()?)
+            })
+            .map_err(|e| match e {
+                VotdError::Status(reqwest::StatusCode::BAD_REQUEST)
+                | VotdError::Status(reqwest::StatusCode::NOT_FOUND) => {
+                    VotdError::NotFound(passage.to_owned())
+                }
+                e => e,
+            })?;
+        decode(&body, passage)
+    }
+}
+
+impl VerseProvider for NetBibleProvider {
+    fn name(&self) -> &str {
+        "net"
+    }
+
+    fn translation(&self) -> &str {
+        "NET"
+    }
+
+    fn lookup(&self, reference: &Reference) -> Result<Verse> {
+        self.fetch(&reference.to_string())
+    }
+
+    fn votd(&self) -> Result<Verse> {
+        self.fetch("votd")
+    }
+
+    fn random(&self) -> Result<Verse> {
+        self.fetch("random")
+    }
+
+    fn timezone(&self) -> Tz {
+        // bible.org is based in Dallas.
+        chrono_tz::America::Chicago
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markup::{StyleSpan, TextStyle};
+    use crate::network::mock;
+
+    const JOHN_3_16: &str = "For this is the way God loved the world: He gave his one and only Son, so that everyone who believes in him will not perish but have eternal life.";
+
+    fn verse(book: &str, chapter: u32, verse: u32, text: &str, headings: &[&str]) -> VerseText {
+        VerseText {
+            book: book.to_owned(),
+            chapter,
+            verse,
+            text: text.to_owned(),
+            styles: Vec::new(),
+            headings: headings.iter().map(|&heading| heading.to_owned()).collect(),
+        }
+    }
+
+    fn bold(start: usize, end: usize) -> StyleSpan {
+        StyleSpan {
+            start,
+            end,
+            style: TextStyle::Bold,
+        }
+    }
+
+    fn decoded(body: &str) -> Vec<VerseText> {
+        decode(body, "John 3:16").unwrap().verses
+    }
+
+    /// A provider that sends its requests to `address`, retrying up to
+    /// `retries` times.
+    fn provider_at(address: &str, retries: u32) -> NetBibleProvider {
+        let network = NetworkOptions {
+            retries,
+            retry_delay: Duration::from_millis(1),
+            proxy: Some("none".to_owned()),
+            ..NetworkOptions::default()
+        };
+        NetBibleProvider {
+            url: reqwest::Url::parse(&format!("http://{}/api/?type=json", address)).unwrap(),
+            ..NetBibleProvider::with_network(&network).unwrap()
+        }
+    }
+
+    #[test]
+    fn retries_after_a_server_error() {
+        let (address, server) = mock::serve(vec![
+            (503, ""),
+            (
+                200,
+                include_str!("../../tests/fixtures/net/string-numbers.json"),
+            ),
+        ]);
+        let passage = provider_at(&address, 1)
+            .lookup(&"John 3:16".parse().unwrap())
+            .unwrap();
+        assert_eq!(passage.verses, [verse("John", 3, 16, JOHN_3_16, &[])]);
+        let requests = server.join().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(
+            requests[0],
+            "GET /api/?type=json&passage=John+3%3A16 HTTP/1.1"
+        );
+        assert_eq!(requests[0], requests[1]);
+    }
+
+    #[test]
+    fn bad_requests_are_not_found_without_retrying() {
+        // Only one response is served, so a retry couldn't connect.
+        let (address, server) = mock::serve(vec![(400, "")]);
+        let result = provider_at(&address, 2).lookup(&"John 3:16".parse().unwrap());
+        assert!(
+            matches!(&result, Err(VotdError::NotFound(passage)) if passage == "John 3:16"),
+            "{:?}",
+            result
+        );
+        assert_eq!(server.join().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn decodes_chapters_and_verses_as_strings_or_numbers() {
+        let expected = [verse("John", 3, 16, JOHN_3_16, &[])];
+        assert_eq!(
+            decoded(include_str!("../../tests/fixtures/net/string-numbers.json")),
+            expected
+        );
+        assert_eq!(
+            decoded(include_str!(
+                "../../tests/fixtures/net/numeric-numbers.json"
+            )),
+            expected
+        );
+    }
+
+    #[test]
+    fn decodes_a_title_as_a_string() {
+        assert_eq!(
+            decoded(include_str!("../../tests/fixtures/net/title-string.json")),
+            [verse(
+                "John",
+                3,
+                1,
+                "Now a certain man, a Pharisee named Nicodemus, who was a member of the Jewish ruling council,",
+                &["Conversation with Nicodemus"]
+            )]
+        );
+    }
+
+    #[test]
+    fn decodes_a_title_as_an_array() {
+        assert_eq!(
+            decoded(include_str!("../../tests/fixtures/net/title-array.json")),
+            [VerseText {
+                styles: vec![bold(4, 8)],
+                ..verse(
+                    "Psalms",
+                    23,
+                    1,
+                    "The Lord is my shepherd, I lack nothing.",
+                    &["Psalm 23", "The Lord Is My Shepherd"]
+                )
+            }]
+        );
+    }
+
+    #[test]
+    fn keeps_a_superscription_as_a_heading() {
+        assert_eq!(
+            decoded(include_str!("../../tests/fixtures/net/superscription.json")),
+            [VerseText {
+                styles: vec![bold(0, 4)],
+                ..verse(
+                    "Psalms",
+                    3,
+                    1,
+                    "Lord, how numerous are my enemies!",
+                    &["A psalm of David, written when he fled from his son Absalom."]
+                )
+            }]
+        );
+    }
+
+    #[test]
+    fn decodes_a_single_object() {
+        assert_eq!(
+            decoded(include_str!("../../tests/fixtures/net/single-object.json")),
+            [verse("John", 11, 35, "Jesus wept.", &[])]
+        );
+    }
+
+    #[test]
+    fn no_verses_is_not_found() {
+        let result = decode(
+            include_str!("../../tests/fixtures/net/empty.json"),
+            "John 3:99",
+        );
+        assert!(
+            matches!(&result, Err(VotdError::NotFound(passage)) if passage == "John 3:99"),
+            "{:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn a_web_page_is_malformed() {
+        let result = decode(include_str!("../../tests/fixtures/net/error.html"), "votd");
+        match result {
+            Err(VotdError::MalformedResponse(message)) => assert_eq!(
+                message,
+                "received a web page instead of verses; the API may be down"
+            ),
+            result => panic!("{:?}", result),
+        }
+    }
+
+    #[test]
+    fn a_non_numeric_chapter_is_malformed() {
+        let result = decode(
+            include_str!("../../tests/fixtures/net/bad-chapter.json"),
+            "John 3:16",
+        );
+        match result {
+            Err(VotdError::MalformedResponse(message)) => {
+                assert_eq!(message, "chapter \"three\" is not a valid integer")
+            }
+            result => panic!("{:?}", result),
+        }
+    }
+}
@@ -0,0 +1,551 @@
+//! The full-screen reader behind `votd read`, showing a chapter at a time.
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Print, SetAttribute};
+use crossterm::{cursor, execute, queue, terminal};
+use std::io::{self, Write};
+use votd::books::{self, Book};
+use votd::render::TextLayout;
+use votd::versification::VerseId;
+use votd::{Bookmarks, Canon, Reference, Verse, VerseText};
+
+/// The most search results listed at once.
+pub const SEARCH_LIMIT: usize = 200;
+
+const HELP: &str = "n/p chapter  : go to  / search  m bookmark  ' bookmarks  q quit";
+
+/// Looks up a chapter for the reader, along with the translation it's in.
+pub type Lookup<'a> = Box<dyn FnMut(&Reference) -> votd::Result<(Verse, String)> + 'a>;
+
+/// Searches for verses containing every given word.
+pub type Search<'a> = Box<dyn Fn(&str) -> votd::Result<Vec<VerseText>> + 'a>;
+
+/// The size of the terminal as (columns, rows), or a guess if it can't be
+/// determined.
+fn screen_size() -> (usize, usize) {
+    terminal_size::terminal_size()
+        .map(|(terminal_size::Width(w), terminal_size::Height(h))| (w as usize, h as usize))
+        .unwrap_or((80, 24))
+}
+
+/// Switches the terminal to a raw alternate screen, and back when dropped
+/// (including on panics and errors).
+struct Screen;
+
+impl Screen {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        let screen = Screen;
+        execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(screen)
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Prompt {
+    GoTo,
+    Search,
+}
+
+/// An entry in a list of search results or bookmarks.
+struct ListItem {
+    label: String,
+    /// Where choosing the entry goes, if it's a valid reference.
+    target: Option<VerseId>,
+    /// The bookmark the entry is for, so it can be removed.
+    bookmark: Option<String>,
+}
+
+enum Mode {
+    Reading,
+    Prompt {
+        prompt: Prompt,
+        input: String,
+    },
+    List {
+        title: String,
+        items: Vec<ListItem>,
+        selected: usize,
+    },
+}
+
+/// What to do after handling a key.
+enum Step {
+    Continue,
+    Quit,
+}
+
+pub struct Reader<'a> {
+    canon: Canon,
+    layout: TextLayout,
+    /// The translation of the chapter shown.
+    translation: String,
+    lookup: Lookup<'a>,
+    search: Option<Search<'a>>,
+    bookmarks: Option<Bookmarks>,
+    book: &'static Book,
+    chapter: u32,
+    verse: Verse,
+    /// The chapter laid out for the terminal, including its title.
+    lines: Vec<String>,
+    /// Each verse's number, and the line it starts on.
+    starts: Vec<(u32, usize)>,
+    /// The first line shown.
+    top: usize,
+    mode: Mode,
+    /// Shown in place of the help until the next key is pressed.
+    message: Option<String>,
+}
+
+impl<'a> Reader<'a> {
+    /// Opens the chapter `start` is in, scrolled to it. Verses are laid out
+    /// with `layout`, except that each starts on its own line and they're
+    /// always wrapped to the terminal.
+    pub fn new(
+        start: VerseId,
+        canon: Canon,
+        layout: TextLayout,
+        mut lookup: Lookup<'a>,
+        search: Option<Search<'a>>,
+        bookmarks: Option<Bookmarks>,
+    ) -> votd::Result<Self> {
+        let (verse, translation) =
+            lookup(&Reference::from_chapter(start.book, start.chapter, canon))?;
+        let mut reader = Reader {
+            canon,
+            layout,
+            translation,
+            lookup,
+            search,
+            bookmarks,
+            book: start.book,
+            chapter: start.chapter,
+            verse,
+            lines: Vec::new(),
+            starts: Vec::new(),
+            top: 0,
+            mode: Mode::Reading,
+            message: None,
+        };
+        reader.lay_out();
+        reader.scroll_to(start.verse);
+        Ok(reader)
+    }
+
+    /// Shows the reader until it's quit.
+    pub fn run(mut self) -> io::Result<()> {
+        let _screen = Screen::enter()?;
+        let mut stdout = io::stdout();
+        loop {
+            self.draw(&mut stdout)?;
+            match event::read()? {
+                Event::Key(key) if key.kind != KeyEventKind::Release => {
+                    if let Step::Quit = self.key(key) {
+                        return Ok(());
+                    }
+                }
+                Event::Resize(..) => {
+                    let verse = self.top_verse();
+                    self.lay_out();
+                    self.scroll_to(verse);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// The number of rows the text can take up, leaving the status line.
+    fn rows(&self) -> usize {
+        screen_size().1.saturating_sub(1).max(1)
+    }
+
+    fn lay_out(&mut self) {
+        let width = screen_size().0;
+        let layout = TextLayout {
+            width: Some(width.max(1)),
+            ..self.layout
+        };
+        let title = if self.book.is_single_chapter() {
+            self.book.name.to_owned()
+        } else {
+            format!("{} {}", self.book.name, self.chapter)
+        };
+        self.lines = vec![title, String::new()];
+        self.starts.clear();
+        for (current, lines) in self
+            .verse
+            .verses
+            .iter()
+            .zip(layout.lines_by_verse(&self.verse))
+        {
+            self.starts.push((current.verse, self.lines.len()));
+            self.lines.extend(lines);
+        }
+    }
+
+    fn max_top(&self) -> usize {
+        self.lines.len().saturating_sub(self.rows())
+    }
+
+    fn scroll_by(&mut self, lines: isize) {
+        let top = self.top as isize + lines;
+        self.top = (top.max(0) as usize).min(self.max_top());
+    }
+
+    /// Scrolls so `verse` (or the headings it starts) is at the top; the
+    /// start of the chapter stays in view for its first verse.
+    fn scroll_to(&mut self, verse: u32) {
+        let line = match self.starts.iter().position(|(number, _)| *number == verse) {
+            Some(0) | None => 0,
+            Some(i) => self.starts[i].1,
+        };
+        self.top = line.min(self.max_top());
+    }
+
+    /// The verse at the top of the screen.
+    fn top_verse(&self) -> u32 {
+        self.starts
+            .iter()
+            .take_while(|(_, line)| *line <= self.top)
+            .last()
+            .or_else(|| self.starts.first())
+            .map_or(1, |(verse, _)| *verse)
+    }
+
+    /// Goes to `target`, looking its chapter up if it isn't the one shown.
+    fn open(&mut self, target: VerseId) {
+        if (target.book, target.chapter) != (self.book, self.chapter) {
+            let reference = Reference::from_chapter(target.book, target.chapter, self.canon);
+            match (self.lookup)(&reference) {
+                Ok((verse, translation)) => {
+                    self.book = target.book;
+                    self.chapter = target.chapter;
+                    self.verse = verse;
+                    self.translation = translation;
+                    self.lay_out();
+                }
+                Err(e) => {
+                    self.message = Some(e.to_string());
+                    return;
+                }
+            }
+        }
+        self.scroll_to(target.verse);
+    }
+
+    fn next_chapter(&mut self, forward: bool) {
+        let chapter = if forward {
+            self.canon.next_chapter(self.book, self.chapter)
+        } else {
+            self.canon.previous_chapter(self.book, self.chapter)
+        };
+        match chapter {
+            Some((book, chapter)) => self.open(VerseId {
+                book,
+                chapter,
+                verse: 1,
+            }),
+            None if forward => self.message = Some("This is the end of the Bible".to_owned()),
+            None => self.message = Some("This is the start of the Bible".to_owned()),
+        }
+    }
+
+    /// Bookmarks the verse at the top of the screen, or removes the bookmark
+    /// if it's already there.
+    fn toggle_bookmark(&mut self) {
+        let reference = VerseId {
+            book: self.book,
+            chapter: self.chapter,
+            verse: self.top_verse(),
+        }
+        .to_string();
+        let bookmarks = match &mut self.bookmarks {
+            Some(bookmarks) => bookmarks,
+            None => {
+                self.message = Some("Can't determine where to keep bookmarks".to_owned());
+                return;
+            }
+        };
+        let message = if bookmarks.add(&reference) {
+            format!("Bookmarked {}", reference)
+        } else {
+            bookmarks.remove(&reference);
+            format!("Removed the bookmark for {}", reference)
+        };
+        self.message = Some(match bookmarks.save() {
+            Ok(()) => message,
+            Err(e) => e.to_string(),
+        });
+    }
+
+    fn list_bookmarks(&mut self) {
+        let references = self
+            .bookmarks
+            .as_ref()
+            .map_or(&[][..], |bookmarks| bookmarks.references());
+        if references.is_empty() {
+            self.message = Some("No bookmarks yet; press m to add one".to_owned());
+            return;
+        }
+        let items = references
+            .iter()
+            .map(|reference| ListItem {
+                label: reference.clone(),
+                target: Reference::parse_with(reference, self.canon)
+                    .ok()
+                    .map(|parsed| parsed.first_verse()),
+                bookmark: Some(reference.clone()),
+            })
+            .collect();
+        self.mode = Mode::List {
+            title: "Bookmarks (d removes one)".to_owned(),
+            items,
+            selected: 0,
+        };
+    }
+
+    fn submit(&mut self, prompt: Prompt, input: &str) {
+        if input.trim().is_empty() {
+            return;
+        }
+        match prompt {
+            Prompt::GoTo => match Reference::parse_with(input, self.canon) {
+                Ok(reference) => self.open(reference.first_verse()),
+                Err(e) => self.message = Some(e.to_string()),
+            },
+            Prompt::Search => {
+                let search = match &self.search {
+                    Some(search) => search,
+                    None => {
+                        self.message = Some(format!(
+                            "Searching needs an imported translation, and {} isn't imported",
+                            self.translation
+                        ));
+                        return;
+                    }
+                };
+                let found = match search(input) {
+                    Ok(found) => found,
+                    Err(e) => {
+                        self.message = Some(e.to_string());
+                        return;
+                    }
+                };
+                if found.is_empty() {
+                    self.message = Some(format!("No verses contain {:?}", input));
+                    return;
+                }
+                let title = if found.len() >= SEARCH_LIMIT {
+                    format!("The first {} verses containing {:?}", found.len(), input)
+                } else {
+                    format!("{} verses containing {:?}", found.len(), input)
+                };
+                let items = found
+                    .into_iter()
+                    .map(|verse| ListItem {
+                        label: format!(
+                            "{} {}:{}  {}",
+                            verse.book,
+                            verse.chapter,
+                            verse.verse,
+                            verse.text.trim()
+                        ),
+                        target: books::find(&verse.book).map(|book| VerseId {
+                            book,
+                            chapter: verse.chapter,
+                            verse: verse.verse,
+                        }),
+                        bookmark: None,
+                    })
+                    .collect();
+                self.mode = Mode::List {
+                    title,
+                    items,
+                    selected: 0,
+                };
+            }
+        }
+    }
+
+    fn key(&mut self, key: KeyEvent) -> Step {
+        self.message = None;
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Step::Quit;
+        }
+        let page = self.rows().saturating_sub(1).max(1) as isize;
+        match std::mem::replace(&mut self.mode, Mode::Reading) {
+            Mode::Reading => match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Step::Quit,
+                KeyCode::Down | KeyCode::Char('j') | KeyCode::Enter => self.scroll_by(1),
+                KeyCode::Up | KeyCode::Char('k') => self.scroll_by(-1),
+                KeyCode::PageDown | KeyCode::Char(' ') => self.scroll_by(page),
+                KeyCode::PageUp | KeyCode::Char('b') => self.scroll_by(-page),
+                KeyCode::Home | KeyCode::Char('g') => self.top = 0,
+                KeyCode::End | KeyCode::Char('G') => self.top = self.max_top(),
+                KeyCode::Right | KeyCode::Char('n') => self.next_chapter(true),
+                KeyCode::Left | KeyCode::Char('p') => self.next_chapter(false),
+                KeyCode::Char(':') => {
+                    self.mode = Mode::Prompt {
+                        prompt: Prompt::GoTo,
+                        input: String::new(),
+                    }
+                }
+                KeyCode::Char('/') => {
+                    self.mode = Mode::Prompt {
+                        prompt: Prompt::Search,
+                        input: String::new(),
+                    }
+                }
+                KeyCode::Char('m') => self.toggle_bookmark(),
+                KeyCode::Char('\'') => self.list_bookmarks(),
+                _ => {}
+            },
+            Mode::Prompt { prompt, mut input } => match key.code {
+                KeyCode::Esc => {}
+                KeyCode::Enter => self.submit(prompt, &input),
+                KeyCode::Backspace => {
+                    input.pop();
+                    self.mode = Mode::Prompt { prompt, input };
+                }
+                KeyCode::Char(c) => {
+                    input.push(c);
+                    self.mode = Mode::Prompt { prompt, input };
+                }
+                _ => self.mode = Mode::Prompt { prompt, input },
+            },
+            Mode::List {
+                title,
+                mut items,
+                mut selected,
+            } => {
+                let last = items.len().saturating_sub(1);
+                match key.code {
+                    KeyCode::Esc | KeyCode::Char('q') => return Step::Continue,
+                    KeyCode::Enter => {
+                        match items[selected].target {
+                            Some(target) => self.open(target),
+                            None => {
+                                self.message = Some(format!(
+                                    "{} isn't a valid reference",
+                                    items[selected].label
+                                ))
+                            }
+                        }
+                        return Step::Continue;
+                    }
+                    KeyCode::Down | KeyCode::Char('j') => selected = (selected + 1).min(last),
+                    KeyCode::Up | KeyCode::Char('k') => selected = selected.saturating_sub(1),
+                    KeyCode::PageDown | KeyCode::Char(' ') => {
+                        selected = (selected + page as usize).min(last)
+                    }
+                    KeyCode::PageUp | KeyCode::Char('b') => {
+                        selected = selected.saturating_sub(page as usize)
+                    }
+                    KeyCode::Home | KeyCode::Char('g') => selected = 0,
+                    KeyCode::End | KeyCode::Char('G') => selected = last,
+                    KeyCode::Char('d') => {
+                        if let (Some(reference), Some(bookmarks)) =
+                            (&items[selected].bookmark, &mut self.bookmarks)
+                        {
+                            bookmarks.remove(reference);
+                            if let Err(e) = bookmarks.save() {
+                                self.message = Some(e.to_string());
+                            }
+                            items.remove(selected);
+                            if items.is_empty() {
+                                return Step::Continue;
+                            }
+                            selected = selected.min(items.len() - 1);
+                        }
+                    }
+                    _ => {}
+                }
+                self.mode = Mode::List {
+                    title,
+                    items,
+                    selected,
+                };
+            }
+        }
+        Step::Continue
+    }
+
+    fn draw(&self, out: &mut impl Write) -> io::Result<()> {
+        let (width, _) = screen_size();
+        let rows = self.rows();
+        queue!(out, terminal::Clear(terminal::ClearType::All))?;
+        if let Mode::List {
+            title,
+            items,
+            selected,
+        } = &self.mode
+        {
+            queue!(
+                out,
+                cursor::MoveTo(0, 0),
+                SetAttribute(Attribute::Bold),
+                Print(truncate(title, width)),
+                SetAttribute(Attribute::Reset)
+            )?;
+            // Keep the selected entry in view below the title.
+            let shown = rows.saturating_sub(1).max(1);
+            let first = selected.saturating_sub(shown - 1);
+            for (row, (i, item)) in items.iter().enumerate().skip(first).take(shown).enumerate() {
+                queue!(out, cursor::MoveTo(0, row as u16 + 1))?;
+                if i == *selected {
+                    queue!(out, SetAttribute(Attribute::Reverse))?;
+                }
+                queue!(
+                    out,
+                    Print(truncate(&item.label, width)),
+                    SetAttribute(Attribute::Reset)
+                )?;
+            }
+        } else {
+            for (row, line) in self.lines.iter().skip(self.top).take(rows).enumerate() {
+                queue!(out, cursor::MoveTo(0, row as u16), Print(line))?;
+            }
+        }
+
+        let status = match (&self.mode, &self.message) {
+            (Mode::Prompt { prompt, input }, _) => match prompt {
+                Prompt::GoTo => format!("Go to: {}", input),
+                Prompt::Search => format!("Search: {}", input),
+            },
+            (_, Some(message)) => message.clone(),
+            (Mode::List { .. }, None) => "Enter opens  q goes back".to_owned(),
+            (Mode::Reading, None) => {
+                let end = (self.top + rows).min(self.lines.len());
+                format!(
+                    "{} {}:{} ({})  {}%  {}",
+                    self.book.name,
+                    self.chapter,
+                    self.top_verse(),
+                    self.translation,
+                    end * 100 / self.lines.len().max(1),
+                    HELP
+                )
+            }
+        };
+        queue!(
+            out,
+            cursor::MoveTo(0, rows as u16),
+            SetAttribute(Attribute::Reverse),
+            Print(format!("{:<1$}", truncate(&status, width), width)),
+            SetAttribute(Attribute::Reset)
+        )?;
+        out.flush()
+    }
+}
+
+/// The first `width` characters of `text`.
+fn truncate(text: &str, width: usize) -> String {
+    text.chars().take(width).collect()
+}
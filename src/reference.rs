@@ -0,0 +1,855 @@
+//! Parsing of Bible references, such as "John 3:16", "Gen 1:30-2:3", or
+//! "John 3:16,18; Rom 8:28".
+
+use crate::books::{self, Book};
+use crate::versification::{Canon, VerseId};
+use std::fmt;
+use std::str::FromStr;
+
+/// A point in a book: a whole chapter, or a verse within one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    pub chapter: u32,
+    /// `None` means the start of the chapter when beginning a passage, or
+    /// its end when ending one.
+    pub verse: Option<u32>,
+}
+
+/// A contiguous run of verses within one book.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Passage {
+    pub book: &'static Book,
+    pub start: Location,
+    pub end: Location,
+}
+
+/// A parsed reference to one or more passages, checked against the
+/// versification of a [`Canon`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    passages: Vec<Passage>,
+    canon: Canon,
+}
+
+/// Why a reference couldn't be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReferenceError {
+    /// Nothing was given to parse.
+    Empty,
+    /// The book name wasn't recognized.
+    UnknownBook {
+        name: String,
+        suggestions: Vec<&'static str>,
+    },
+    /// The book name could refer to more than one book.
+    AmbiguousBook {
+        name: String,
+        candidates: Vec<&'static str>,
+    },
+    /// A passage gave a chapter or verse without any book before it.
+    MissingBook { passage: String },
+    /// A passage's chapter and verse numbers couldn't be understood.
+    Syntax { passage: String, message: String },
+    /// The book isn't part of the canon being used.
+    NotInCanon {
+        book: &'static str,
+        canon: Canon,
+        other_canons: Vec<Canon>,
+    },
+    /// The book doesn't have that many chapters.
+    ChapterOutOfRange {
+        book: &'static str,
+        chapter: u32,
+        chapters: u32,
+        /// Canons in which the chapter does exist.
+        other_canons: Vec<Canon>,
+    },
+    /// The chapter doesn't have that many verses.
+    VerseOutOfRange {
+        book: &'static str,
+        chapter: u32,
+        verse: u32,
+        verses: u32,
+    },
+    /// A range ends before it starts.
+    Backwards { passage: String },
+}
+
+impl fmt::Display for ReferenceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReferenceError::Empty => write!(f, "no reference given"),
+            ReferenceError::UnknownBook { name, suggestions } => {
+                write!(f, "unknown book {:?}", name)?;
+                if !suggestions.is_empty() {
+                    write!(f, "; did you mean {}?", suggestions.join(" or "))?;
+                }
+                Ok(())
+            }
+            ReferenceError::AmbiguousBook { name, candidates } => {
+                write!(f, "{:?} could mean any of: {}", name, candidates.join(", "))
+            }
+            ReferenceError::MissingBook { passage } => {
+                write!(f, "{:?} doesn't say which book it's in", passage)
+            }
+            ReferenceError::Syntax { passage, message } => write!(f, "{:?}: {}", passage, message),
+            ReferenceError::NotInCanon {
+                book,
+                canon,
+                other_canons,
+            } => {
+                write!(f, "{} isn't part of the {} canon", book, canon)?;
+                write_canons(f, "it's in", other_canons)
+            }
+            ReferenceError::ChapterOutOfRange {
+                book,
+                chapter,
+                chapters,
+                other_canons,
+            } => {
+                write!(f, "{} has no chapter {}; ", book, chapter)?;
+                if *chapters == 1 {
+                    write!(f, "it only has 1 chapter")?;
+                } else {
+                    write!(f, "it has {} chapters", chapters)?;
+                }
+                write_canons(f, &format!("chapter {} is in", chapter), other_canons)
+            }
+            ReferenceError::VerseOutOfRange {
+                book,
+                chapter,
+                verse,
+                verses,
+            } => write!(
+                f,
+                "{} {} has no verse {}; it has {} verses",
+                book, chapter, verse, verses
+            ),
+            ReferenceError::Backwards { passage } => {
+                write!(f, "{:?} ends before it starts", passage)
+            }
+        }
+    }
+}
+
+/// Writes " (<prefix> the X and Y canons)", if there are any canons.
+fn write_canons(f: &mut fmt::Formatter<'_>, prefix: &str, canons: &[Canon]) -> fmt::Result {
+    let names: Vec<String> = canons.iter().map(Canon::to_string).collect();
+    match names.as_slice() {
+        [] => Ok(()),
+        [one] => write!(f, " ({} the {} canon)", prefix, one),
+        [rest @ .., last] => write!(
+            f,
+            " ({} the {} and {} canons)",
+            prefix,
+            rest.join(", "),
+            last
+        ),
+    }
+}
+
+impl std::error::Error for ReferenceError {}
+
+impl Location {
+    /// The whole of `chapter`.
+    pub fn chapter(chapter: u32) -> Self {
+        Location {
+            chapter,
+            verse: None,
+        }
+    }
+
+    /// A single verse.
+    pub fn verse(chapter: u32, verse: u32) -> Self {
+        Location {
+            chapter,
+            verse: Some(verse),
+        }
+    }
+}
+
+impl Passage {
+    /// The whole of `book`, as divided in `canon`.
+    pub fn whole_book(book: &'static Book, canon: Canon) -> Self {
+        Passage {
+            book,
+            start: Location::chapter(1),
+            end: Location::chapter(canon.chapters(book).unwrap_or(book.chapters)),
+        }
+    }
+
+    /// Whether this passage is the whole of its book, as divided in `canon`.
+    pub fn is_whole_book(&self, canon: Canon) -> bool {
+        *self == Passage::whole_book(self.book, canon)
+    }
+
+    /// The first verse of the passage.
+    pub fn first_verse(&self) -> VerseId {
+        VerseId {
+            book: self.book,
+            chapter: self.start.chapter,
+            verse: self.start.verse.unwrap_or(1),
+        }
+    }
+
+    /// The last verse of the passage, using `canon` to find the end of a
+    /// chapter.
+    pub fn last_verse(&self, canon: Canon) -> VerseId {
+        VerseId {
+            book: self.book,
+            chapter: self.end.chapter,
+            verse: self
+                .end
+                .verse
+                .or_else(|| canon.verses(self.book, self.end.chapter))
+                .unwrap_or(1),
+        }
+    }
+
+    /// Every verse in the passage, in order.
+    pub fn verses(&self, canon: Canon) -> Vec<VerseId> {
+        let last = self.last_verse(canon);
+        let mut verses = vec![self.first_verse()];
+        while let Some(next) = verses.last().and_then(|verse| canon.next_verse(*verse)) {
+            if verses.last() == Some(&last) || next.book != self.book {
+                break;
+            }
+            verses.push(next);
+        }
+        verses
+    }
+
+    /// Whether this passage is made up of whole chapters.
+    pub fn is_whole_chapters(&self) -> bool {
+        self.start.verse.is_none() && self.end.verse.is_none()
+    }
+}
+
+impl Passage {
+    /// Writes the passage as a reference, as divided in `canon`, so that a
+    /// whole book is written as just its name whichever canon it's in.
+    fn write(&self, f: &mut fmt::Formatter<'_>, canon: Canon) -> fmt::Result {
+        write!(f, "{}", self.book.name)?;
+        let Passage { start, end, .. } = self;
+        if self.is_whole_book(canon) {
+            return Ok(());
+        }
+        if self.is_whole_chapters() {
+            write!(f, " {}", start.chapter)?;
+            if end.chapter != start.chapter {
+                write!(f, "-{}", end.chapter)?;
+            }
+            return Ok(());
+        }
+        write!(f, " {}:{}", start.chapter, start.verse.unwrap_or(1))?;
+        match end.verse {
+            Some(verse) if end.chapter == start.chapter => {
+                if Some(verse) != start.verse {
+                    write!(f, "-{}", verse)?;
+                }
+            }
+            Some(verse) => write!(f, "-{}:{}", end.chapter, verse)?,
+            None => write!(f, "-{}", end.chapter)?,
+        }
+        Ok(())
+    }
+}
+
+impl Reference {
+    /// Parses a reference, checking it against the versification of
+    /// `canon`. Parsing with [`str::parse`] uses the Protestant canon.
+    pub fn parse_with(input: &str, canon: Canon) -> Result<Self, ReferenceError> {
+        if input.trim().is_empty() {
+            return Err(ReferenceError::Empty);
+        }
+        let mut passages: Vec<Passage> = Vec::new();
+        let mut separator = None;
+        let mut rest = input;
+        loop {
+            let (segment, next) = match rest.find([',', ';']) {
+                Some(i) => (&rest[..i], Some(rest.as_bytes()[i] as char)),
+                None => (rest, None),
+            };
+            if segment.trim().is_empty() {
+                return Err(ReferenceError::Syntax {
+                    passage: input.trim().to_owned(),
+                    message: "empty passage in list".to_owned(),
+                });
+            }
+            let passage = parse_passage(segment.trim(), passages.last(), separator, canon)?;
+            passages.push(passage);
+            match next {
+                Some(c) => {
+                    separator = Some(c);
+                    rest = &rest[segment.len() + 1..];
+                }
+                None => break,
+            }
+        }
+        Ok(Reference { passages, canon })
+    }
+
+    /// A reference to a single passage.
+    pub fn from_passage(passage: Passage, canon: Canon) -> Self {
+        Reference {
+            passages: vec![passage],
+            canon,
+        }
+    }
+
+    /// A reference to a single verse.
+    pub fn from_verse(verse: VerseId, canon: Canon) -> Self {
+        let location = Location::verse(verse.chapter, verse.verse);
+        Reference::from_passage(
+            Passage {
+                book: verse.book,
+                start: location,
+                end: location,
+            },
+            canon,
+        )
+    }
+
+    /// A reference to a whole chapter.
+    pub fn from_chapter(book: &'static Book, chapter: u32, canon: Canon) -> Self {
+        Reference::from_passage(
+            Passage {
+                book,
+                start: Location::chapter(chapter),
+                end: Location::chapter(chapter),
+            },
+            canon,
+        )
+    }
+
+    /// A reference to `verses` (which mustn't be empty), in as few passages
+    /// as possible: each run of consecutive verses becomes one passage,
+    /// written as whole chapters if it starts and ends on chapter bounds.
+    pub fn from_verses(verses: &[VerseId], canon: Canon) -> Self {
+        let mut runs: Vec<(VerseId, VerseId)> = Vec::new();
+        for &verse in verses {
+            match runs.last_mut() {
+                Some((_, last))
+                    if last.book == verse.book && canon.next_verse(*last) == Some(verse) =>
+                {
+                    *last = verse
+                }
+                _ => runs.push((verse, verse)),
+            }
+        }
+        let passages = runs
+            .into_iter()
+            .map(|(first, last)| {
+                let whole_chapters =
+                    first.verse == 1 && canon.verses(last.book, last.chapter) == Some(last.verse);
+                let (start, end) = if whole_chapters {
+                    (
+                        Location::chapter(first.chapter),
+                        Location::chapter(last.chapter),
+                    )
+                } else {
+                    (
+                        Location::verse(first.chapter, first.verse),
+                        Location::verse(last.chapter, last.verse),
+                    )
+                };
+                Passage {
+                    book: first.book,
+                    start,
+                    end,
+                }
+            })
+            .collect();
+        Reference { passages, canon }
+    }
+
+    /// Whether every passage is made up of whole chapters, e.g. "John 3" or
+    /// "Ruth".
+    pub fn is_whole_chapters(&self) -> bool {
+        self.passages.iter().all(Passage::is_whole_chapters)
+    }
+
+    /// Splits this reference into pages of at most `limit` verses, or if
+    /// `limit` isn't given, one page per chapter.
+    pub fn pages(&self, limit: Option<usize>) -> Vec<Reference> {
+        let verses = self.verses();
+        let mut pages: Vec<Vec<VerseId>> = Vec::new();
+        for verse in verses {
+            let new_page = match (pages.last(), limit) {
+                (None, _) => true,
+                (Some(page), Some(limit)) => page.len() >= limit.max(1),
+                (Some(page), None) => page.last().map_or(true, |last| {
+                    last.book != verse.book || last.chapter != verse.chapter
+                }),
+            };
+            if new_page {
+                pages.push(Vec::new());
+            }
+            if let Some(page) = pages.last_mut() {
+                page.push(verse);
+            }
+        }
+        pages
+            .iter()
+            .map(|page| Reference::from_verses(page, self.canon))
+            .collect()
+    }
+
+    /// The passages making up this reference, in the order given.
+    pub fn passages(&self) -> &[Passage] {
+        &self.passages
+    }
+
+    /// The canon this reference was checked against.
+    pub fn canon(&self) -> Canon {
+        self.canon
+    }
+
+    /// Every verse this reference covers, in the order given.
+    pub fn verses(&self) -> Vec<VerseId> {
+        self.passages
+            .iter()
+            .flat_map(|passage| passage.verses(self.canon))
+            .collect()
+    }
+
+    /// The first verse of the first passage.
+    pub fn first_verse(&self) -> VerseId {
+        self.passages[0].first_verse()
+    }
+
+    /// The last verse of the last passage.
+    pub fn last_verse(&self) -> VerseId {
+        self.passages[self.passages.len() - 1].last_verse(self.canon)
+    }
+
+    /// The verse following this reference, if it isn't the end of the Bible.
+    pub fn next_verse(&self) -> Option<VerseId> {
+        self.canon.next_verse(self.last_verse())
+    }
+
+    /// The verse preceding this reference, if it isn't the start of the
+    /// Bible.
+    pub fn previous_verse(&self) -> Option<VerseId> {
+        self.canon.previous_verse(self.first_verse())
+    }
+
+    /// The first and last verses of the chapter this reference starts in,
+    /// or `None` if the chapter doesn't exist in its canon (as can happen
+    /// for a reference built with [`Reference::from_chapter`] or
+    /// [`Reference::from_verses`] rather than parsed).
+    pub fn chapter_bounds(&self) -> Option<(VerseId, VerseId)> {
+        let first = self.first_verse();
+        self.canon.chapter_bounds(first.book, first.chapter)
+    }
+}
+
+impl fmt::Display for Reference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, passage) in self.passages.iter().enumerate() {
+            if i > 0 {
+                write!(f, "; ")?;
+            }
+            passage.write(f, self.canon)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Reference {
+    type Err = ReferenceError;
+
+    fn from_str(input: &str) -> Result<Self, ReferenceError> {
+        Reference::parse_with(input, Canon::default())
+    }
+}
+
+fn resolve_book(name: &str) -> Result<&'static Book, ReferenceError> {
+    if let Some(book) = books::find(name) {
+        return Ok(book);
+    }
+    match books::find_by_prefix(name).as_slice() {
+        [book] => Ok(book),
+        [] => Err(ReferenceError::UnknownBook {
+            name: name.to_owned(),
+            suggestions: books::suggest(name).iter().map(|b| b.name).collect(),
+        }),
+        candidates => Err(ReferenceError::AmbiguousBook {
+            name: name.to_owned(),
+            candidates: candidates.iter().map(|b| b.name).collect(),
+        }),
+    }
+}
+
+/// Splits "1 John 3:16" into "1 John" and "3:16".
+fn split_book(segment: &str) -> (&str, &str) {
+    let is_location_char = |c: char| c.is_ascii_digit() || c.is_whitespace() || ":.-–—".contains(c);
+    let location_start = segment
+        .char_indices()
+        .rev()
+        .take_while(|&(_, c)| is_location_char(c))
+        .last()
+        .map_or(segment.len(), |(i, _)| i);
+    // Punctuation between the book and the numbers ("Jn. 3") belongs to the
+    // book.
+    let location_start = segment[location_start..]
+        .find(|c: char| c.is_ascii_digit())
+        .map_or(segment.len(), |i| location_start + i);
+    (segment[..location_start].trim(), &segment[location_start..])
+}
+
+/// A chapter, or a chapter and verse, as written.
+type Point = (u32, Option<u32>);
+
+fn parse_point(point: &str, passage: &str) -> Result<Point, ReferenceError> {
+    let syntax = |message: &str| ReferenceError::Syntax {
+        passage: passage.to_owned(),
+        message: message.to_owned(),
+    };
+    let number = |n: &str| -> Result<u32, ReferenceError> {
+        match n.parse::<u32>() {
+            Ok(0) => Err(syntax("chapters and verses start at 1")),
+            Ok(n) => Ok(n),
+            Err(_) if n.is_empty() => Err(syntax("expected a number")),
+            Err(_) => Err(syntax(&format!("{:?} isn't a number", n))),
+        }
+    };
+    match point.split_once([':', '.']) {
+        Some((chapter, verse)) => Ok((number(chapter)?, Some(number(verse)?))),
+        None => Ok((number(point)?, None)),
+    }
+}
+
+fn parse_passage(
+    segment: &str,
+    previous: Option<&Passage>,
+    separator: Option<char>,
+    canon: Canon,
+) -> Result<Passage, ReferenceError> {
+    let syntax = |message: &str| ReferenceError::Syntax {
+        passage: segment.to_owned(),
+        message: message.to_owned(),
+    };
+    let (book_name, location) = split_book(segment);
+    // Book names only have digits before any letters ("1 John"), so anything
+    // else means the chapter and verse weren't understood.
+    let letters_start = book_name.find(char::is_alphabetic);
+    if letters_start.map_or(false, |i| {
+        book_name[i..].contains(|c: char| c.is_ascii_digit() || c == ':')
+    }) {
+        return Err(syntax("couldn't understand the chapter and verse"));
+    }
+    let (book, continued) = if book_name.is_empty() {
+        match previous {
+            Some(previous) => (previous.book, Some(previous)),
+            None => {
+                return Err(ReferenceError::MissingBook {
+                    passage: segment.to_owned(),
+                })
+            }
+        }
+    } else if !book_name.contains(char::is_alphabetic) {
+        return Err(syntax("expected a book name"));
+    } else {
+        (resolve_book(book_name)?, None)
+    };
+    let chapters = match canon.chapters(book) {
+        Some(chapters) => chapters,
+        None => {
+            return Err(ReferenceError::NotInCanon {
+                book: book.name,
+                canon,
+                other_canons: Canon::ALL
+                    .iter()
+                    .copied()
+                    .filter(|other| other.contains(book))
+                    .collect(),
+            })
+        }
+    };
+
+    let location: String = location
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| if c == '–' || c == '—' { '-' } else { c })
+        .collect();
+    if location.is_empty() {
+        return Ok(Passage::whole_book(book, canon));
+    }
+    let (start, end) = match location.split_once('-') {
+        Some((start, end)) if !end.contains('-') => (
+            parse_point(start, segment)?,
+            Some(parse_point(end, segment)?),
+        ),
+        Some(_) => return Err(syntax("a range can only have one '-'")),
+        None => (parse_point(&location, segment)?, None),
+    };
+
+    // A lone number is a verse in single-chapter books ("Jude 3"), and when
+    // continuing a list of verses ("John 3:16,18"); otherwise it's a chapter.
+    let start = match start {
+        (chapter, Some(verse)) => Location::verse(chapter, verse),
+        (n, None) if book.is_single_chapter() => Location::verse(1, n),
+        (n, None) => match continued {
+            Some(previous) if separator == Some(',') && previous.end.verse.is_some() => {
+                Location::verse(previous.end.chapter, n)
+            }
+            _ => Location::chapter(n),
+        },
+    };
+    let end = match end {
+        None => start,
+        Some((chapter, Some(verse))) => Location::verse(chapter, verse),
+        Some((n, None)) if start.verse.is_some() => Location::verse(start.chapter, n),
+        Some((n, None)) => Location::chapter(n),
+    };
+
+    for location in [start, end] {
+        let verses = match canon.verses(book, location.chapter) {
+            Some(verses) => verses,
+            None => {
+                return Err(ReferenceError::ChapterOutOfRange {
+                    book: book.name,
+                    chapter: location.chapter,
+                    chapters,
+                    other_canons: canon.others_with_chapter(book, location.chapter),
+                })
+            }
+        };
+        match location.verse {
+            Some(verse) if verse > verses => {
+                return Err(ReferenceError::VerseOutOfRange {
+                    book: book.name,
+                    chapter: location.chapter,
+                    verse,
+                    verses,
+                })
+            }
+            _ => {}
+        }
+    }
+    let starts_after_end = match (start.verse, end.verse) {
+        _ if start.chapter != end.chapter => start.chapter > end.chapter,
+        (Some(start), Some(end)) => start > end,
+        _ => false,
+    };
+    if starts_after_end {
+        return Err(ReferenceError::Backwards {
+            passage: segment.to_owned(),
+        });
+    }
+    Ok(Passage { book, start, end })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> Reference {
+        input
+            .parse()
+            .unwrap_or_else(|e| panic!("{:?}: {}", input, e))
+    }
+
+    fn passage(book: &str, start: Location, end: Location) -> Passage {
+        Passage {
+            book: books::find(book).unwrap(),
+            start,
+            end,
+        }
+    }
+
+    fn verses(book: &str, start: (u32, u32), end: (u32, u32)) -> Passage {
+        passage(
+            book,
+            Location::verse(start.0, start.1),
+            Location::verse(end.0, end.1),
+        )
+    }
+
+    #[test]
+    fn parses_book_names_and_ordinals() {
+        let first_john = [verses("1 John", (4, 8), (4, 8))];
+        for input in [
+            "1 John 4:8",
+            "1John 4:8",
+            "1Jn 4:8",
+            "I Jn 4:8",
+            "I John 4:8",
+            "1 jn. 4:8",
+        ] {
+            assert_eq!(parse(input).passages(), first_john, "{:?}", input);
+        }
+        assert_eq!(
+            parse("Jn 3:16").passages(),
+            [verses("John", (3, 16), (3, 16))]
+        );
+        assert_eq!(
+            parse("Ps 23:1").passages(),
+            [verses("Psalms", (23, 1), (23, 1))]
+        );
+    }
+
+    #[test]
+    fn parses_chapters_and_books() {
+        assert_eq!(
+            parse("John 3").passages(),
+            [passage("John", Location::chapter(3), Location::chapter(3))]
+        );
+        assert_eq!(
+            parse("John 3-4").passages(),
+            [passage("John", Location::chapter(3), Location::chapter(4))]
+        );
+        assert_eq!(
+            parse("Ruth").passages(),
+            [passage("Ruth", Location::chapter(1), Location::chapter(4))]
+        );
+        // A lone number in a single-chapter book is a verse.
+        assert_eq!(parse("Jude 3").passages(), [verses("Jude", (1, 3), (1, 3))]);
+    }
+
+    #[test]
+    fn parses_ranges() {
+        assert_eq!(
+            parse("John 3:16-18").passages(),
+            [verses("John", (3, 16), (3, 18))]
+        );
+        assert_eq!(
+            parse("Gen 1:30-2:3").passages(),
+            [verses("Genesis", (1, 30), (2, 3))]
+        );
+        assert_eq!(
+            parse("Gen 1:30 – 2:3").passages(),
+            [verses("Genesis", (1, 30), (2, 3))]
+        );
+    }
+
+    #[test]
+    fn parses_lists() {
+        assert_eq!(
+            parse("John 3:16,18; Rom 8:28").passages(),
+            [
+                verses("John", (3, 16), (3, 16)),
+                verses("John", (3, 18), (3, 18)),
+                verses("Romans", (8, 28), (8, 28)),
+            ]
+        );
+        // After a semicolon, a lone number is a chapter of the same book.
+        assert_eq!(
+            parse("John 3:16; 4").passages(),
+            [
+                verses("John", (3, 16), (3, 16)),
+                passage("John", Location::chapter(4), Location::chapter(4)),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_references() {
+        let error = |input: &str| input.parse::<Reference>().unwrap_err();
+        assert_eq!(error("  "), ReferenceError::Empty);
+        assert!(matches!(
+            error("Jhon 3:16"),
+            ReferenceError::UnknownBook { suggestions, .. } if suggestions.contains(&"John")
+        ));
+        assert!(matches!(
+            error("Ma 1:1"),
+            ReferenceError::AmbiguousBook { .. }
+        ));
+        assert_eq!(
+            error("3:16"),
+            ReferenceError::MissingBook {
+                passage: "3:16".to_owned()
+            }
+        );
+        assert!(matches!(error("John 3:x"), ReferenceError::Syntax { .. }));
+        assert!(matches!(error("John 0:1"), ReferenceError::Syntax { .. }));
+        assert!(matches!(
+            error("John 3:1-2-3"),
+            ReferenceError::Syntax { .. }
+        ));
+        assert!(matches!(
+            error("John 3:16,,18"),
+            ReferenceError::Syntax { .. }
+        ));
+        assert_eq!(
+            error("John 3:18-16"),
+            ReferenceError::Backwards {
+                passage: "John 3:18-16".to_owned()
+            }
+        );
+        assert!(matches!(
+            error("Tobit 1"),
+            ReferenceError::NotInCanon { .. }
+        ));
+    }
+
+    #[test]
+    fn displays_in_a_form_that_parses_back() {
+        for (input, displayed) in [
+            ("jn 3:16", "John 3:16"),
+            ("I Jn 4:8-10", "1 John 4:8-10"),
+            ("Gen 1:30-2:3", "Genesis 1:30-2:3"),
+            (
+                "John 3:16,18; Rom 8:28",
+                "John 3:16; John 3:18; Romans 8:28",
+            ),
+            ("John 3", "John 3"),
+            ("John 3-4", "John 3-4"),
+            ("Ruth 1-4", "Ruth"),
+            ("Jude 3", "Jude 1:3"),
+        ] {
+            let reference = parse(input);
+            assert_eq!(reference.to_string(), displayed);
+            assert_eq!(parse(displayed), reference);
+        }
+    }
+
+    #[test]
+    fn whole_books_display_as_their_name_in_every_canon() {
+        for canon in Canon::ALL {
+            for name in ["Daniel", "Esther", "Psalms"] {
+                let reference = Reference::parse_with(name, canon).unwrap();
+                assert_eq!(reference.to_string(), name, "{}", canon);
+            }
+        }
+        // The chapters of the shorter book aren't the whole of the longer.
+        let daniel = Reference::parse_with("Daniel 1-12", Canon::Catholic).unwrap();
+        assert_eq!(daniel.to_string(), "Daniel 1-12");
+        let psalms = Reference::parse_with("Psalms 1-150", Canon::Orthodox).unwrap();
+        assert_eq!(psalms.to_string(), "Psalms 1-150");
+    }
+
+    #[test]
+    fn chapter_bounds_are_only_found_for_chapters_in_the_canon() {
+        let john = books::find("John").unwrap();
+        let bounds = Reference::from_chapter(john, 3, Canon::Protestant).chapter_bounds();
+        assert_eq!(
+            bounds,
+            Some((
+                VerseId {
+                    book: john,
+                    chapter: 3,
+                    verse: 1
+                },
+                VerseId {
+                    book: john,
+                    chapter: 3,
+                    verse: 36
+                },
+            ))
+        );
+        assert_eq!(
+            Reference::from_chapter(john, 22, Canon::Protestant).chapter_bounds(),
+            None
+        );
+        let daniel = books::find("Daniel").unwrap();
+        assert_eq!(
+            Reference::from_chapter(daniel, 13, Canon::Protestant).chapter_bounds(),
+            None
+        );
+        assert!(Reference::from_chapter(daniel, 13, Canon::Catholic)
+            .chapter_bounds()
+            .is_some());
+    }
+}
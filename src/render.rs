@@ -0,0 +1,377 @@
+//! Laying out the text of a passage for the terminal.
+
+use crate::markup::{self, StyleSpan, TextStyle};
+use crate::verse::{Verse, VerseText};
+use std::fmt;
+use std::str::FromStr;
+
+/// How verse numbers are written when shown inline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NumberStyle {
+    /// Superscript digits, e.g. "¹⁶For God so loved".
+    #[default]
+    Superscript,
+    /// Numbers in brackets, e.g. "[16] For God so loved".
+    Brackets,
+}
+
+impl fmt::Display for NumberStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            NumberStyle::Superscript => "superscript",
+            NumberStyle::Brackets => "brackets",
+        })
+    }
+}
+
+/// The names accepted when parsing a [`NumberStyle`].
+pub const NUMBER_STYLE_NAMES: &[&str] = &["superscript", "brackets"];
+
+impl FromStr for NumberStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "superscript" | "sup" => Ok(NumberStyle::Superscript),
+            "brackets" | "bracket" => Ok(NumberStyle::Brackets),
+            _ => Err(format!(
+                "unknown number style {:?}; expected one of: {}",
+                s,
+                NUMBER_STYLE_NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+/// Options for laying out the text of a passage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TextLayout {
+    /// Show each verse's number before its text.
+    pub verse_numbers: Option<NumberStyle>,
+    /// Start each verse on its own line.
+    pub one_per_line: bool,
+    /// Wrap lines to this many columns.
+    pub width: Option<usize>,
+    /// Show the source's bold and italic text using ANSI escape sequences.
+    pub emphasis: bool,
+    /// Show section headings on their own lines above the verses they start.
+    pub headings: bool,
+}
+
+fn superscript(number: &str) -> String {
+    number
+        .chars()
+        .map(|c| match c {
+            '0' => '⁰',
+            '1' => '¹',
+            '2' => '²',
+            '3' => '³',
+            '4' => '⁴',
+            '5' => '⁵',
+            '6' => '⁶',
+            '7' => '⁷',
+            '8' => '⁸',
+            '9' => '⁹',
+            c => c,
+        })
+        .collect()
+}
+
+impl TextLayout {
+    /// The number to show before `verse`; the chapter is included when it
+    /// differs from the previous verse's (the title already gives the first
+    /// verse's chapter).
+    fn number(style: NumberStyle, verse: &VerseText, previous: Option<&VerseText>) -> String {
+        let new_chapter = previous.map_or(false, |previous| {
+            previous.book != verse.book || previous.chapter != verse.chapter
+        });
+        let number = if new_chapter {
+            format!("{}:{}", verse.chapter, verse.verse)
+        } else {
+            verse.verse.to_string()
+        };
+        match style {
+            NumberStyle::Superscript => superscript(&number),
+            NumberStyle::Brackets => format!("[{}] ", number),
+        }
+    }
+
+    /// Each verse's text, with its number if requested.
+    fn verse_texts(&self, verse: &Verse) -> Vec<String> {
+        let mut previous = None;
+        verse
+            .verses
+            .iter()
+            .map(|current| {
+                let styled;
+                let text = if self.emphasis {
+                    styled = markup::to_ansi(&current.text, &current.styles);
+                    styled.trim()
+                } else {
+                    current.text.trim()
+                };
+                let text = match self.verse_numbers {
+                    Some(style) => {
+                        format!("{}{}", TextLayout::number(style, current, previous), text)
+                    }
+                    None => text.to_owned(),
+                };
+                previous = Some(current);
+                text
+            })
+            .collect()
+    }
+
+    fn wrap(&self, paragraph: &str, lines: &mut Vec<String>) {
+        match self.width {
+            Some(width) => lines.extend(
+                textwrap::wrap(paragraph, width)
+                    .into_iter()
+                    .map(|line| line.into_owned()),
+            ),
+            None => lines.push(paragraph.to_owned()),
+        }
+    }
+
+    /// Wraps `text`, which may already contain line breaks, to the layout's
+    /// width.
+    pub fn wrap_text(&self, text: &str) -> Vec<String> {
+        let mut lines = Vec::new();
+        self.wrap(text, &mut lines);
+        lines
+    }
+
+    fn heading(&self, heading: &str) -> String {
+        if self.emphasis {
+            let bold = [StyleSpan {
+                start: 0,
+                end: heading.len(),
+                style: TextStyle::Bold,
+            }];
+            markup::to_ansi(heading, &bold)
+        } else {
+            heading.to_owned()
+        }
+    }
+
+    /// Lays out each verse of `verse` on lines of its own, along with the
+    /// headings it starts, so a verse's place in the text can be found.
+    pub fn lines_by_verse(&self, verse: &Verse) -> Vec<Vec<String>> {
+        let texts = self.verse_texts(verse);
+        let mut laid_out = Vec::with_capacity(texts.len());
+        for (i, (current, text)) in verse.verses.iter().zip(&texts).enumerate() {
+            let mut lines = Vec::new();
+            if self.headings && !current.headings.is_empty() {
+                if i > 0 {
+                    lines.push(String::new());
+                }
+                for heading in &current.headings {
+                    let heading = self.heading(heading.trim());
+                    self.wrap(&heading, &mut lines);
+                }
+            }
+            self.wrap(text, &mut lines);
+            laid_out.push(lines);
+        }
+        laid_out
+    }
+
+    /// Lays out the text of `verse` as lines, without trailing newlines.
+    pub fn lines(&self, verse: &Verse) -> Vec<String> {
+        let texts = self.verse_texts(verse);
+        let mut lines = Vec::new();
+        let mut paragraph: Vec<&str> = Vec::new();
+        for (current, text) in verse.verses.iter().zip(&texts) {
+            if self.headings && !current.headings.is_empty() {
+                if !paragraph.is_empty() {
+                    self.wrap(&paragraph.join(" "), &mut lines);
+                    paragraph.clear();
+                }
+                if !lines.is_empty() {
+                    lines.push(String::new());
+                }
+                for heading in &current.headings {
+                    let heading = self.heading(heading.trim());
+                    self.wrap(&heading, &mut lines);
+                }
+            }
+            if self.one_per_line {
+                self.wrap(text, &mut lines);
+            } else {
+                paragraph.push(text);
+            }
+        }
+        if !paragraph.is_empty() {
+            self.wrap(&paragraph.join(" "), &mut lines);
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn verse(chapter: u32, verse: u32, text: &str) -> VerseText {
+        VerseText {
+            book: "John".to_owned(),
+            chapter,
+            verse,
+            text: text.to_owned(),
+            styles: Vec::new(),
+            headings: Vec::new(),
+        }
+    }
+
+    /// John 3:35-36 and 4:1, with a heading and some emphasis.
+    fn passage() -> Verse {
+        Verse {
+            verses: vec![
+                verse(
+                    3,
+                    35,
+                    "The Father loves the Son and has placed all things under his authority.",
+                ),
+                VerseText {
+                    styles: vec![StyleSpan {
+                        start: 4,
+                        end: 20,
+                        style: TextStyle::Italic,
+                    }],
+                    ..verse(3, 36, "The one who believes in the Son has eternal life.")
+                },
+                VerseText {
+                    headings: vec!["Conversation with a Samaritan Woman".to_owned()],
+                    ..verse(4, 1, "Now when Jesus knew that the Pharisees had heard.")
+                },
+            ],
+        }
+    }
+
+    /// `line` without its ANSI escape sequences.
+    fn strip_ansi(line: &str) -> String {
+        let mut stripped = String::new();
+        let mut chars = line.chars();
+        while let Some(c) = chars.next() {
+            if c == '\x1b' {
+                chars.by_ref().find(|&c| c == 'm');
+            } else {
+                stripped.push(c);
+            }
+        }
+        stripped
+    }
+
+    #[test]
+    fn joins_verses_into_a_paragraph() {
+        assert_eq!(
+            TextLayout::default().lines(&passage()),
+            [
+                "The Father loves the Son and has placed all things under his authority. \
+              The one who believes in the Son has eternal life. \
+              Now when Jesus knew that the Pharisees had heard."
+            ]
+        );
+    }
+
+    #[test]
+    fn numbers_verses_with_the_chapter_when_it_changes() {
+        let superscript = TextLayout {
+            verse_numbers: Some(NumberStyle::Superscript),
+            one_per_line: true,
+            ..TextLayout::default()
+        };
+        let lines = superscript.lines(&passage());
+        assert!(lines[0].starts_with("³⁵The Father"), "{}", lines[0]);
+        assert!(lines[1].starts_with("³⁶The one"), "{}", lines[1]);
+        assert!(lines[2].starts_with("⁴:¹Now when"), "{}", lines[2]);
+
+        let brackets = TextLayout {
+            verse_numbers: Some(NumberStyle::Brackets),
+            ..superscript
+        };
+        let lines = brackets.lines(&passage());
+        assert!(lines[0].starts_with("[35] The Father"), "{}", lines[0]);
+        assert!(lines[2].starts_with("[4:1] Now when"), "{}", lines[2]);
+    }
+
+    #[test]
+    fn lays_out_each_verse_with_its_headings() {
+        let layout = TextLayout {
+            headings: true,
+            ..TextLayout::default()
+        };
+        assert_eq!(
+            layout.lines_by_verse(&passage()),
+            [
+                vec!["The Father loves the Son and has placed all things under his authority."],
+                vec!["The one who believes in the Son has eternal life."],
+                vec![
+                    "",
+                    "Conversation with a Samaritan Woman",
+                    "Now when Jesus knew that the Pharisees had heard.",
+                ],
+            ]
+        );
+        // Without headings, the verses are just their text.
+        assert_eq!(
+            TextLayout::default().lines_by_verse(&passage())[2],
+            ["Now when Jesus knew that the Pharisees had heard."]
+        );
+    }
+
+    #[test]
+    fn wraps_to_the_width() {
+        let layout = TextLayout {
+            width: Some(30),
+            ..TextLayout::default()
+        };
+        let lines = layout.lines_by_verse(&passage());
+        assert_eq!(
+            lines[1],
+            ["The one who believes in the", "Son has eternal life."]
+        );
+        assert!(lines
+            .iter()
+            .flatten()
+            .all(|line| line.chars().count() <= 30));
+        assert_eq!(
+            layout.wrap_text("For God so loved the world\nthat he gave his one and only Son"),
+            [
+                "For God so loved the world",
+                "that he gave his one and only",
+                "Son"
+            ]
+        );
+        assert_eq!(
+            TextLayout::default().wrap_text("Jesus wept."),
+            ["Jesus wept."]
+        );
+    }
+
+    #[test]
+    fn emphasis_is_only_shown_when_asked_for() {
+        let plain = TextLayout {
+            width: Some(30),
+            ..TextLayout::default()
+        };
+        let emphasis = TextLayout {
+            emphasis: true,
+            ..plain
+        };
+        let plain_lines = plain.lines_by_verse(&passage());
+        assert!(!plain_lines
+            .iter()
+            .flatten()
+            .any(|line| line.contains('\x1b')));
+        // Escape sequences don't count towards the width, so the text wraps
+        // in the same places.
+        let styled = emphasis.lines_by_verse(&passage());
+        assert!(styled[1][0].contains('\x1b'));
+        let stripped: Vec<Vec<String>> = styled
+            .iter()
+            .map(|lines| lines.iter().map(|line| strip_ansi(line)).collect())
+            .collect();
+        assert_eq!(stripped, plain_lines);
+    }
+}
@@ -0,0 +1,420 @@
+//! Parsers for the file formats a translation can be imported from.
+
+use crate::books;
+use crate::error::{Result, VotdError};
+use crate::markup;
+use quick_xml::events::Event;
+use std::str::FromStr;
+
+/// A verse read from an import file.
+#[derive(Debug, Clone)]
+pub(crate) struct ImportedVerse {
+    pub book: String,
+    pub chapter: u32,
+    pub verse: u32,
+    pub text: String,
+}
+
+/// The formats a translation can be imported from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// OSIS XML, with either container or milestone `<verse>` elements.
+    Osis,
+    /// USFM, one or more books concatenated.
+    Usfm,
+    /// A JSON array of `{ "book", "chapter", "verse", "text" }` objects.
+    Json,
+    /// CSV with `book,chapter,verse,text` columns and an optional header.
+    Csv,
+}
+
+impl ImportFormat {
+    /// Guesses the format from a file's extension.
+    pub fn from_path(path: &std::path::Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "xml" | "osis" => Some(ImportFormat::Osis),
+            "usfm" | "sfm" | "ptx" => Some(ImportFormat::Usfm),
+            "json" => Some(ImportFormat::Json),
+            "csv" => Some(ImportFormat::Csv),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for ImportFormat {
+    type Err = VotdError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "osis" | "xml" => Ok(ImportFormat::Osis),
+            "usfm" | "sfm" => Ok(ImportFormat::Usfm),
+            "json" => Ok(ImportFormat::Json),
+            "csv" => Ok(ImportFormat::Csv),
+            _ => Err(VotdError::Import(format!(
+                "unknown format {:?}; expected one of: osis, usfm, json, csv",
+                s
+            ))),
+        }
+    }
+}
+
+pub(crate) fn parse(source: &str, format: ImportFormat) -> Result<Vec<ImportedVerse>> {
+    let verses = match format {
+        ImportFormat::Osis => parse_osis(source)?,
+        ImportFormat::Usfm => parse_usfm(source)?,
+        ImportFormat::Json => parse_json(source)?,
+        ImportFormat::Csv => parse_csv(source)?,
+    };
+    if verses.is_empty() {
+        return Err(VotdError::Import("no verses found".to_owned()));
+    }
+    Ok(verses)
+}
+
+fn book_name(id: &str) -> String {
+    books::find(id)
+        .map(|book| book.name.to_owned())
+        .unwrap_or_else(|| id.trim().to_owned())
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn parse_osis(source: &str) -> Result<Vec<ImportedVerse>> {
+    let mut reader = quick_xml::Reader::from_str(source);
+    let mut verses = Vec::new();
+    let mut current: Option<(String, u32, u32)> = None;
+    let mut text = String::new();
+    // Depth inside elements whose text isn't part of the verse (footnotes and
+    // headings).
+    let mut skip = 0usize;
+
+    fn osis_ref(id: &str) -> Option<(String, u32, u32)> {
+        let first = id.split_whitespace().next()?;
+        let mut parts = first.split('.');
+        let book = parts.next()?;
+        let chapter = parts.next()?.parse().ok()?;
+        let verse = parts.next()?.parse().ok()?;
+        Some((book_name(book), chapter, verse))
+    }
+
+    let mut finish = |current: &mut Option<(String, u32, u32)>, text: &mut String| {
+        if let Some((book, chapter, verse)) = current.take() {
+            verses.push(ImportedVerse {
+                book,
+                chapter,
+                verse,
+                text: collapse_whitespace(text),
+            });
+        }
+        text.clear();
+    };
+
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|e| VotdError::Import(format!("invalid OSIS XML: {}", e)))?;
+        match event {
+            Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == b"verse" => {
+                let attr = |name: &str| -> Result<Option<String>> {
+                    match e.try_get_attribute(name) {
+                        Ok(Some(attr)) => Ok(Some(
+                            attr.unescape_value()
+                                .map_err(|e| VotdError::Import(e.to_string()))?
+                                .into_owned(),
+                        )),
+                        Ok(None) => Ok(None),
+                        Err(e) => Err(VotdError::Import(e.to_string())),
+                    }
+                };
+                if attr("eID")?.is_some() {
+                    finish(&mut current, &mut text);
+                } else if let Some(id) = attr("osisID")?.or(attr("sID")?) {
+                    finish(&mut current, &mut text);
+                    current = osis_ref(&id);
+                }
+            }
+            Event::End(e) if e.local_name().as_ref() == b"verse" => {
+                finish(&mut current, &mut text);
+            }
+            Event::Start(e) if matches!(e.local_name().as_ref(), b"note" | b"title") => {
+                skip += 1;
+            }
+            Event::End(e) if matches!(e.local_name().as_ref(), b"note" | b"title") => {
+                skip = skip.saturating_sub(1);
+            }
+            Event::Text(e) if current.is_some() && skip == 0 => {
+                let unescaped = e
+                    .unescape()
+                    .map_err(|e| VotdError::Import(format!("invalid OSIS XML: {}", e)))?;
+                text.push_str(&unescaped);
+            }
+            Event::CData(e) if current.is_some() && skip == 0 => {
+                text.push_str(&String::from_utf8_lossy(&e));
+            }
+            Event::Start(_) | Event::Empty(_) | Event::End(_) if current.is_some() => {
+                // Keep words on either side of inline elements apart.
+                text.push(' ');
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    finish(&mut current, &mut text);
+    Ok(verses)
+}
+
+/// Markers whose remaining line is metadata or a heading, not verse text.
+const USFM_LINE_MARKERS: &[&str] = &[
+    "id", "ide", "h", "toc", "toca", "mt", "mte", "ms", "mr", "s", "sr", "r", "rem", "sts", "cl",
+    "cd", "d", "sp", "usfm", "imt", "is", "ip", "ipi", "im", "io", "iot", "ili", "ie",
+];
+
+/// Markers whose content (up to the matching closing marker) is a note.
+const USFM_NOTE_MARKERS: &[&str] = &["f", "fe", "ef", "x", "ex", "fig"];
+
+fn parse_usfm(source: &str) -> Result<Vec<ImportedVerse>> {
+    let mut verses = Vec::new();
+    let mut book: Option<String> = None;
+    let mut chapter = 0u32;
+    let mut verse: Option<u32> = None;
+    let mut text = String::new();
+    let mut note_depth = 0usize;
+
+    let mut finish =
+        |book: &Option<String>, chapter: u32, verse: &mut Option<u32>, text: &mut String| {
+            if let (Some(book), Some(number)) = (book, verse.take()) {
+                verses.push(ImportedVerse {
+                    book: book.clone(),
+                    chapter,
+                    verse: number,
+                    text: collapse_whitespace(text),
+                });
+            }
+            text.clear();
+        };
+
+    for (line_number, line) in source.lines().enumerate() {
+        let mut rest = line;
+        while !rest.is_empty() {
+            let (plain, marker) = match rest.find('\\') {
+                Some(i) => (&rest[..i], Some(&rest[i + 1..])),
+                None => (rest, None),
+            };
+            if note_depth == 0 && verse.is_some() {
+                // Drop word attributes, as in `\w word|strong="G25"\w*`.
+                let plain = plain.split('|').next().unwrap_or(plain);
+                text.push_str(plain);
+            }
+            let marker = match marker {
+                Some(marker) => marker,
+                None => break,
+            };
+            let name_len = marker
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '+'))
+                .unwrap_or(marker.len());
+            let name = marker[..name_len].trim_start_matches('+');
+            let closing = marker[name_len..].starts_with('*');
+            rest = &marker[name_len..];
+            // Skip the `*` of a closing marker or the space after an opening one.
+            if closing || rest.starts_with(' ') {
+                rest = &rest[1..];
+            }
+            let base = name.trim_end_matches(|c: char| c.is_ascii_digit());
+
+            if USFM_NOTE_MARKERS.contains(&base) {
+                if closing {
+                    note_depth = note_depth.saturating_sub(1);
+                } else {
+                    note_depth += 1;
+                }
+                continue;
+            }
+            if closing {
+                continue;
+            }
+            let mut word = || -> &str {
+                let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                let word = &rest[..end];
+                rest = rest[end..].trim_start();
+                word
+            };
+            match base {
+                "id" => {
+                    finish(&book, chapter, &mut verse, &mut text);
+                    book = Some(book_name(word()));
+                    chapter = 0;
+                    rest = "";
+                }
+                "c" => {
+                    finish(&book, chapter, &mut verse, &mut text);
+                    let number = word();
+                    chapter = number.parse().map_err(|_| {
+                        VotdError::Import(format!(
+                            "line {}: invalid chapter number {:?}",
+                            line_number + 1,
+                            number
+                        ))
+                    })?;
+                }
+                "v" => {
+                    finish(&book, chapter, &mut verse, &mut text);
+                    let number = word();
+                    // Verse bridges ("3-4") are stored under their first verse.
+                    let first = number.split(['-', ',']).next().unwrap_or(number);
+                    let first = first.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+                    verse = Some(first.parse().map_err(|_| {
+                        VotdError::Import(format!(
+                            "line {}: invalid verse number {:?}",
+                            line_number + 1,
+                            number
+                        ))
+                    })?);
+                }
+                _ if USFM_LINE_MARKERS.contains(&base) => rest = "",
+                _ => text.push(' '),
+            }
+        }
+        text.push(' ');
+    }
+    finish(&book, chapter, &mut verse, &mut text);
+    if verses.iter().any(|verse| verse.chapter == 0) {
+        return Err(VotdError::Import(
+            "verse found outside of a chapter (missing \\c marker)".to_owned(),
+        ));
+    }
+    Ok(verses)
+}
+
+fn parse_json(source: &str) -> Result<Vec<ImportedVerse>> {
+    use serde_json::Value;
+
+    let value: Value = serde_json::from_str(source)
+        .map_err(|e| VotdError::Import(format!("invalid JSON: {}", e)))?;
+    let entries = match &value {
+        Value::Array(entries) => entries,
+        Value::Object(object) => match object.get("verses") {
+            Some(Value::Array(entries)) => entries,
+            _ => return Err(VotdError::Import("expected an array of verses".to_owned())),
+        },
+        _ => return Err(VotdError::Import("expected an array of verses".to_owned())),
+    };
+
+    fn field<'a>(entry: &'a Value, names: &[&str]) -> Option<&'a Value> {
+        names.iter().find_map(|name| entry.get(*name))
+    }
+    fn number(value: Option<&Value>) -> Option<u32> {
+        match value? {
+            Value::Number(n) => n.as_u64().and_then(|n| u32::try_from(n).ok()),
+            Value::String(s) => s.trim().parse().ok(),
+            _ => None,
+        }
+    }
+
+    entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            let invalid = |what: &str| {
+                VotdError::Import(format!("verse {}: missing or invalid {}", i + 1, what))
+            };
+            let book = field(entry, &["book", "bookname", "book_name"])
+                .and_then(Value::as_str)
+                .ok_or_else(|| invalid("book"))?;
+            Ok(ImportedVerse {
+                book: book_name(book),
+                chapter: number(field(entry, &["chapter"])).ok_or_else(|| invalid("chapter"))?,
+                verse: number(field(entry, &["verse"])).ok_or_else(|| invalid("verse"))?,
+                text: field(entry, &["text"])
+                    .and_then(Value::as_str)
+                    .map(|text| markup::normalize(text).text)
+                    .ok_or_else(|| invalid("text"))?,
+            })
+        })
+        .collect()
+}
+
+/// Splits CSV into records, handling quoted fields with embedded commas,
+/// quotes, and newlines.
+fn csv_records(source: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted => {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    quoted = false;
+                }
+            }
+            '"' if field.is_empty() => quoted = true,
+            ',' if !quoted => record.push(std::mem::take(&mut field)),
+            '\r' if !quoted => {}
+            '\n' if !quoted => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records.retain(|record| !(record.len() == 1 && record[0].trim().is_empty()));
+    records
+}
+
+fn parse_csv(source: &str) -> Result<Vec<ImportedVerse>> {
+    let mut records = csv_records(source.trim_start_matches('\u{feff}')).into_iter();
+    let mut columns = [0, 1, 2, 3];
+    let mut first = records.next();
+    if let Some(header) = &first {
+        if header
+            .get(1)
+            .map_or(false, |chapter| chapter.trim().parse::<u32>().is_err())
+        {
+            for (column, name) in columns.iter_mut().zip(["book", "chapter", "verse", "text"]) {
+                *column = header
+                    .iter()
+                    .position(|h| h.trim().eq_ignore_ascii_case(name))
+                    .ok_or_else(|| {
+                        VotdError::Import(format!("CSV header is missing a {:?} column", name))
+                    })?;
+            }
+            first = None;
+        }
+    }
+    first
+        .into_iter()
+        .chain(records)
+        .enumerate()
+        .map(|(i, record)| {
+            let get = |column: usize, what: &str| {
+                record
+                    .get(column)
+                    .map(|s| s.trim())
+                    .ok_or_else(|| VotdError::Import(format!("record {}: missing {}", i + 1, what)))
+            };
+            let number = |column: usize, what: &str| -> Result<u32> {
+                let value = get(column, what)?;
+                value.parse().map_err(|_| {
+                    VotdError::Import(format!("record {}: invalid {} {:?}", i + 1, what, value))
+                })
+            };
+            Ok(ImportedVerse {
+                book: book_name(get(columns[0], "book")?),
+                chapter: number(columns[1], "chapter")?,
+                verse: number(columns[2], "verse")?,
+                text: markup::normalize(get(columns[3], "text")?).text,
+            })
+        })
+        .collect()
+}
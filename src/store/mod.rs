@@ -0,0 +1,279 @@
+//! An offline store of whole translations, so lookups don't need the network.
+//!
+//! Each translation lives in its own directory, with a small index of its
+//! books and one file per book, so a lookup only has to decode the book it
+//! needs.
+
+mod import;
+
+pub use import::ImportFormat;
+
+use crate::books::{self, Book};
+use crate::error::{Result, VotdError};
+use crate::reference::Reference;
+use crate::verse::{Verse, VerseText};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+const INDEX_FILE: &str = "index.mpk";
+
+#[derive(Serialize, Deserialize, Debug)]
+struct StoreIndex {
+    translation: String,
+    books: Vec<IndexedBook>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct IndexedBook {
+    name: String,
+    file: String,
+    /// The number of verses in each chapter.
+    chapters: Vec<u32>,
+}
+
+/// The text of a book; `chapters[c - 1][v - 1]` is the text of verse `v` of
+/// chapter `c`, or empty if the translation omits that verse.
+type BookText = Vec<Vec<String>>;
+
+/// What was imported by [`BibleStore::import`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportSummary {
+    pub books: usize,
+    pub verses: usize,
+}
+
+/// A translation stored on disk.
+#[derive(Debug)]
+pub struct BibleStore {
+    dir: PathBuf,
+    index: StoreIndex,
+}
+
+fn store_error(e: std::io::Error) -> VotdError {
+    VotdError::Store(e)
+}
+
+fn decode<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T> {
+    let bytes = std::fs::read(path).map_err(store_error)?;
+    rmp_serde::from_slice(&bytes).map_err(|e| {
+        VotdError::Store(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("{}: {}", path.display(), e),
+        ))
+    })
+}
+
+fn encode<T: serde::Serialize>(path: &Path, value: &T) -> Result<()> {
+    let bytes = rmp_serde::to_vec(value)
+        .map_err(|e| store_error(std::io::Error::new(std::io::ErrorKind::Other, e)))?;
+    std::fs::write(path, bytes).map_err(store_error)
+}
+
+fn translation_dir(root: &Path, translation: &str) -> PathBuf {
+    root.join(translation.to_ascii_uppercase())
+}
+
+impl BibleStore {
+    /// The default directory translations are stored under, if one can be
+    /// determined.
+    pub fn default_dir() -> Option<PathBuf> {
+        directories::BaseDirs::new().map(|dirs| dirs.data_dir().join("votd").join("bibles"))
+    }
+
+    /// The translations that have been imported under `root`.
+    pub fn installed(root: &Path) -> Result<Vec<String>> {
+        let entries = match std::fs::read_dir(root) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(store_error(e)),
+        };
+        let mut translations = Vec::new();
+        for entry in entries {
+            let entry = entry.map_err(store_error)?;
+            if entry.path().join(INDEX_FILE).is_file() {
+                translations.push(entry.file_name().to_string_lossy().into_owned());
+            }
+        }
+        translations.sort();
+        Ok(translations)
+    }
+
+    /// Opens the stored copy of `translation` under `root`, or returns `None`
+    /// if it hasn't been imported.
+    pub fn open(root: &Path, translation: &str) -> Result<Option<Self>> {
+        let dir = translation_dir(root, translation);
+        let index_path = dir.join(INDEX_FILE);
+        if !index_path.is_file() {
+            return Ok(None);
+        }
+        let index = decode(&index_path)?;
+        Ok(Some(BibleStore { dir, index }))
+    }
+
+    /// Imports the file at `path` as `translation` under `root`, replacing
+    /// any previous copy.
+    pub fn import(
+        root: &Path,
+        translation: &str,
+        path: &Path,
+        format: ImportFormat,
+    ) -> Result<ImportSummary> {
+        let source = std::fs::read_to_string(path).map_err(store_error)?;
+        let verses = import::parse(&source, format)?;
+
+        // Group by book, keeping books in the order they first appear.
+        let mut order: Vec<String> = Vec::new();
+        let mut grouped: BTreeMap<usize, BTreeMap<(u32, u32), String>> = BTreeMap::new();
+        for verse in &verses {
+            if verse.chapter == 0 || verse.verse == 0 {
+                return Err(VotdError::Import(format!(
+                    "{} {}:{} is not a valid verse",
+                    verse.book, verse.chapter, verse.verse
+                )));
+            }
+            let position = match order.iter().position(|book| *book == verse.book) {
+                Some(position) => position,
+                None => {
+                    order.push(verse.book.clone());
+                    order.len() - 1
+                }
+            };
+            grouped
+                .entry(position)
+                .or_default()
+                .insert((verse.chapter, verse.verse), verse.text.clone());
+        }
+
+        let dir = translation_dir(root, translation);
+        if dir.exists() {
+            std::fs::remove_dir_all(&dir).map_err(store_error)?;
+        }
+        std::fs::create_dir_all(&dir).map_err(store_error)?;
+
+        let mut index = StoreIndex {
+            translation: translation.to_ascii_uppercase(),
+            books: Vec::new(),
+        };
+        for (position, book_verses) in grouped {
+            let name = order[position].clone();
+            let mut text: BookText = Vec::new();
+            for ((chapter, verse), verse_text) in book_verses {
+                let (chapter, verse) = (chapter as usize, verse as usize);
+                if text.len() < chapter {
+                    text.resize(chapter, Vec::new());
+                }
+                let verses = &mut text[chapter - 1];
+                if verses.len() < verse {
+                    verses.resize(verse, String::new());
+                }
+                verses[verse - 1] = verse_text;
+            }
+            let file = format!("{}.mpk", position);
+            encode(&dir.join(&file), &text)?;
+            index.books.push(IndexedBook {
+                name,
+                file,
+                chapters: text.iter().map(|verses| verses.len() as u32).collect(),
+            });
+        }
+        // The index is written last, so an interrupted import is never
+        // mistaken for a complete one.
+        encode(&dir.join(INDEX_FILE), &index)?;
+
+        Ok(ImportSummary {
+            books: index.books.len(),
+            verses: verses.len(),
+        })
+    }
+
+    /// The translation this store holds.
+    pub fn translation(&self) -> &str {
+        &self.index.translation
+    }
+
+    fn find_book(&self, book: &Book) -> Option<&IndexedBook> {
+        self.index
+            .books
+            .iter()
+            .find(|stored| stored.name == book.name || books::find(&stored.name) == Some(book))
+    }
+
+    /// Looks up the passages in `reference`, returning `None` if the store
+    /// doesn't have all of them.
+    pub fn lookup(&self, reference: &Reference) -> Result<Option<Verse>> {
+        let mut found = Vec::new();
+        for passage in reference.passages() {
+            let book = match self.find_book(passage.book) {
+                Some(book) => book,
+                None => return Ok(None),
+            };
+            let text: BookText = decode(&self.dir.join(&book.file))?;
+            for chapter in passage.start.chapter..=passage.end.chapter {
+                let verses = match text.get(chapter as usize - 1) {
+                    Some(verses) if !verses.is_empty() => verses,
+                    _ => return Ok(None),
+                };
+                let first = match passage.start.verse {
+                    Some(verse) if chapter == passage.start.chapter => verse as usize,
+                    _ => 1,
+                };
+                let last = match passage.end.verse {
+                    Some(verse) if chapter == passage.end.chapter => verse as usize,
+                    _ => verses.len(),
+                };
+                if last > verses.len() {
+                    return Ok(None);
+                }
+                for (number, text) in verses.iter().enumerate().take(last).skip(first - 1) {
+                    if !text.is_empty() {
+                        found.push(VerseText {
+                            book: book.name.clone(),
+                            chapter,
+                            verse: number as u32 + 1,
+                            text: text.clone(),
+                            styles: Vec::new(),
+                            headings: Vec::new(),
+                        });
+                    }
+                }
+            }
+        }
+        if found.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Verse { verses: found }))
+    }
+
+    /// Finds verses containing every word of `query` (ignoring case), in
+    /// the order they were imported, stopping after `limit` matches.
+    pub fn search(&self, query: &str, limit: usize) -> Result<Vec<VerseText>> {
+        let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+        let mut found = Vec::new();
+        if words.is_empty() {
+            return Ok(found);
+        }
+        for book in &self.index.books {
+            let text: BookText = decode(&self.dir.join(&book.file))?;
+            for (chapter, verses) in text.iter().enumerate() {
+                for (verse, text) in verses.iter().enumerate() {
+                    if found.len() >= limit {
+                        return Ok(found);
+                    }
+                    let lower = text.to_lowercase();
+                    if !text.is_empty() && words.iter().all(|word| lower.contains(word.as_str())) {
+                        found.push(VerseText {
+                            book: book.name.clone(),
+                            chapter: chapter as u32 + 1,
+                            verse: verse as u32 + 1,
+                            text: text.clone(),
+                            styles: Vec::new(),
+                            headings: Vec::new(),
+                        });
+                    }
+                }
+            }
+        }
+        Ok(found)
+    }
+}
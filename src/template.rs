@@ -0,0 +1,405 @@
+//! A small template language for laying out a passage exactly, e.g.
+//! `"{text}\n  — {reference} ({translation})"`.
+//!
+//! - `{field}` is replaced by the field's value. The fields are `reference`,
+//!   `translation`, `text`, `votd` (true or false), `cached` (true or false),
+//!   `cached_at` (seconds since the Unix epoch, or nothing), and `stale`
+//!   (true if an out-of-date cached passage is shown instead of a current
+//!   one).
+//! - `{#verses}...{/verses}` repeats its contents for each verse, inside
+//!   which `book`, `chapter`, `verse`, and `text` refer to that verse,
+//!   `headings` to the section headings it starts (one per line), and
+//!   `first` and `last` are true for the first and last verses.
+//! - `{?field}...{/field}` only includes its contents if the field is true
+//!   (or, for text, not empty); `{!field}...{/field}` only if it isn't.
+//! - `{{` and `}}` are literal braces, and `\n`, `\t`, and `\\` are a
+//!   newline, a tab, and a backslash.
+
+use crate::output::{Document, DocumentVerse};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Reference,
+    Translation,
+    Text,
+    Votd,
+    Cached,
+    CachedAt,
+    Stale,
+    Book,
+    Chapter,
+    Verse,
+    Headings,
+    First,
+    Last,
+}
+
+impl Field {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "reference" => Field::Reference,
+            "translation" => Field::Translation,
+            "text" => Field::Text,
+            "votd" => Field::Votd,
+            "cached" => Field::Cached,
+            "cached_at" => Field::CachedAt,
+            "stale" => Field::Stale,
+            "book" => Field::Book,
+            "chapter" => Field::Chapter,
+            "verse" => Field::Verse,
+            "headings" => Field::Headings,
+            "first" => Field::First,
+            "last" => Field::Last,
+            _ => return None,
+        })
+    }
+
+    /// Whether the field only has a value inside `{#verses}`.
+    fn is_per_verse(self) -> bool {
+        matches!(
+            self,
+            Field::Book
+                | Field::Chapter
+                | Field::Verse
+                | Field::Headings
+                | Field::First
+                | Field::Last
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Literal(String),
+    Field(Field),
+    If {
+        field: Field,
+        negated: bool,
+        body: Vec<Node>,
+    },
+    Verses(Vec<Node>),
+}
+
+/// A parsed output template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+/// The verse being rendered inside `{#verses}`, with its position.
+struct Current<'a> {
+    verse: &'a DocumentVerse<'a>,
+    first: bool,
+    last: bool,
+}
+
+struct Parser<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Parser<'a> {
+    /// Parses nodes until `{/closing}` (or the end, if `closing` is `None`).
+    fn parse(&mut self, closing: Option<&str>, in_verses: bool) -> Result<Vec<Node>, String> {
+        let mut nodes = Vec::new();
+        let mut literal = String::new();
+        loop {
+            let mut chars = self.rest.chars();
+            let c = match chars.next() {
+                Some(c) => c,
+                None => {
+                    return match closing {
+                        Some(name) => Err(format!("{{{}}} is never closed", name)),
+                        None => {
+                            if !literal.is_empty() {
+                                nodes.push(Node::Literal(literal));
+                            }
+                            Ok(nodes)
+                        }
+                    };
+                }
+            };
+            let next = chars.next();
+            match (c, next) {
+                ('{', Some('{')) | ('}', Some('}')) => {
+                    literal.push(c);
+                    self.rest = &self.rest[2..];
+                    continue;
+                }
+                ('\\', Some(escaped @ ('n' | 't' | '\\'))) => {
+                    literal.push(match escaped {
+                        'n' => '\n',
+                        't' => '\t',
+                        _ => '\\',
+                    });
+                    self.rest = &self.rest[2..];
+                    continue;
+                }
+                ('}', _) => return Err("unmatched \"}\"; write \"}}\" for a brace".to_owned()),
+                ('{', _) => {}
+                _ => {
+                    literal.push(c);
+                    self.rest = &self.rest[c.len_utf8()..];
+                    continue;
+                }
+            }
+
+            let end = self
+                .rest
+                .find('}')
+                .ok_or_else(|| "unclosed \"{\"; write \"{{\" for a brace".to_owned())?;
+            let tag = self.rest[1..end].trim();
+            self.rest = &self.rest[end + 1..];
+            if !literal.is_empty() {
+                nodes.push(Node::Literal(std::mem::take(&mut literal)));
+            }
+
+            if let Some(name) = tag.strip_prefix('/') {
+                return match closing {
+                    Some(expected) if expected == name.trim() => Ok(nodes),
+                    Some(expected) => Err(format!(
+                        "expected {{/{}}}, found {{/{}}}",
+                        expected,
+                        name.trim()
+                    )),
+                    None => Err(format!("{{/{}}} closes nothing", name.trim())),
+                };
+            }
+            if let Some(name) = tag.strip_prefix('#') {
+                let name = name.trim();
+                if name != "verses" {
+                    return Err(format!("can't repeat {:?}; only \"verses\" can be", name));
+                }
+                if in_verses {
+                    return Err("{#verses} can't be nested".to_owned());
+                }
+                nodes.push(Node::Verses(self.parse(Some("verses"), true)?));
+                continue;
+            }
+            let (negated, name) = match tag.chars().next() {
+                Some('?') => (Some(false), tag[1..].trim()),
+                Some('!') => (Some(true), tag[1..].trim()),
+                _ => (None, tag),
+            };
+            let field =
+                Field::from_name(name).ok_or_else(|| format!("unknown field {:?}", name))?;
+            if field.is_per_verse() && !in_verses {
+                return Err(format!("{:?} can only be used inside {{#verses}}", name));
+            }
+            match negated {
+                Some(negated) => {
+                    let body = self.parse(Some(name), in_verses)?;
+                    nodes.push(Node::If {
+                        field,
+                        negated,
+                        body,
+                    });
+                }
+                None => nodes.push(Node::Field(field)),
+            }
+        }
+    }
+}
+
+impl FromStr for Template {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let nodes = Parser { rest: s }.parse(None, false)?;
+        Ok(Template { nodes })
+    }
+}
+
+impl Template {
+    /// Renders the template for `document`. `{text}` outside of
+    /// `{#verses}` is replaced by `text`, so the passage can be laid out
+    /// first (e.g. with verse numbers).
+    pub fn render(&self, document: &Document, text: &str) -> String {
+        let mut out = String::new();
+        Template::render_nodes(&self.nodes, document, text, None, &mut out);
+        out
+    }
+
+    fn value(field: Field, document: &Document, text: &str, current: Option<&Current>) -> String {
+        match (field, current) {
+            (Field::Reference, _) => document.reference.clone(),
+            (Field::Translation, _) => document.translation.to_owned(),
+            (Field::Text, Some(current)) => current.verse.text.to_owned(),
+            (Field::Text, None) => text.to_owned(),
+            (Field::Votd, _) => document.votd.to_string(),
+            (Field::Cached, _) => document.cache.hit.to_string(),
+            (Field::CachedAt, _) => document
+                .cache
+                .written_at
+                .map(|at| at.to_string())
+                .unwrap_or_default(),
+            (Field::Stale, _) => document.cache.stale.to_string(),
+            (Field::Book, Some(current)) => current.verse.book.to_owned(),
+            (Field::Chapter, Some(current)) => current.verse.chapter.to_string(),
+            (Field::Verse, Some(current)) => current.verse.verse.to_string(),
+            (Field::Headings, Some(current)) => current.verse.headings.join("\n"),
+            (Field::First, Some(current)) => current.first.to_string(),
+            (Field::Last, Some(current)) => current.last.to_string(),
+            // The parser only allows per-verse fields inside `{#verses}`.
+            (_, None) => String::new(),
+        }
+    }
+
+    fn render_nodes(
+        nodes: &[Node],
+        document: &Document,
+        text: &str,
+        current: Option<&Current>,
+        out: &mut String,
+    ) {
+        for node in nodes {
+            match node {
+                Node::Literal(literal) => out.push_str(literal),
+                Node::Field(field) => {
+                    out.push_str(&Template::value(*field, document, text, current))
+                }
+                Node::If {
+                    field,
+                    negated,
+                    body,
+                } => {
+                    let value = Template::value(*field, document, text, current);
+                    let truthy = !value.is_empty() && value != "false";
+                    if truthy != *negated {
+                        Template::render_nodes(body, document, text, current, out);
+                    }
+                }
+                Node::Verses(body) => {
+                    let count = document.verses.len();
+                    for (i, verse) in document.verses.iter().enumerate() {
+                        let current = Current {
+                            verse,
+                            first: i == 0,
+                            last: i + 1 == count,
+                        };
+                        Template::render_nodes(body, document, text, Some(&current), out);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::CacheInfo;
+    use crate::verse::{Verse, VerseText};
+
+    fn passage() -> Verse {
+        let verse = |verse: u32, text: &str, headings: &[&str]| VerseText {
+            book: "John".to_owned(),
+            chapter: 3,
+            verse,
+            text: text.to_owned(),
+            styles: Vec::new(),
+            headings: headings.iter().map(|&heading| heading.to_owned()).collect(),
+        };
+        Verse {
+            verses: vec![
+                verse(16, "For God so loved the world", &["God's Love"]),
+                verse(17, "that he sent his Son", &[]),
+            ],
+        }
+    }
+
+    fn render(template: &str) -> String {
+        let passage = passage();
+        let cache = CacheInfo {
+            hit: true,
+            written_at: Some(1_792_300_000),
+            stale: false,
+        };
+        let document = Document::new(&passage, "NET", false, cache);
+        template
+            .parse::<Template>()
+            .unwrap_or_else(|e| panic!("{:?}: {}", template, e))
+            .render(&document, "the laid out text")
+    }
+
+    fn error(template: &str) -> String {
+        template.parse::<Template>().unwrap_err()
+    }
+
+    #[test]
+    fn substitutes_fields() {
+        assert_eq!(
+            render("{text}\\n  — {reference} ({translation})"),
+            "the laid out text\n  — John 3:16-17 (NET)"
+        );
+        assert_eq!(
+            render("{votd} {cached} {cached_at} {stale}"),
+            "false true 1792300000 false"
+        );
+        assert_eq!(render("{ reference }"), "John 3:16-17");
+    }
+
+    #[test]
+    fn repeats_for_each_verse() {
+        assert_eq!(
+            render("{#verses}{?headings}[{headings}] {/headings}{chapter}:{verse} {text}{!last}\\n{/last}{/verses}"),
+            "[God's Love] 3:16 For God so loved the world\n3:17 that he sent his Son"
+        );
+        assert_eq!(
+            render("{#verses}{?first}{book} {/first}{verse}{/verses}"),
+            "John 1617"
+        );
+    }
+
+    #[test]
+    fn includes_sections_conditionally() {
+        assert_eq!(
+            render("{?cached}cached{/cached}{!votd}, not votd{/votd}"),
+            "cached, not votd"
+        );
+        assert_eq!(render("{?votd}votd{/votd}{!cached}not cached{/cached}"), "");
+    }
+
+    #[test]
+    fn escapes_braces_and_backslashes() {
+        assert_eq!(
+            render("{{reference}} {{{reference}}}"),
+            "{reference} {John 3:16-17}"
+        );
+        assert_eq!(render("a\\tb\\\\n"), "a\tb\\n");
+        // Other backslashes are kept as they are.
+        assert_eq!(render("a\\b"), "a\\b");
+    }
+
+    #[test]
+    fn rejects_invalid_templates() {
+        assert_eq!(error("{refrence}"), "unknown field \"refrence\"");
+        assert_eq!(
+            error("{reference"),
+            "unclosed \"{\"; write \"{{\" for a brace"
+        );
+        assert_eq!(
+            error("reference}"),
+            "unmatched \"}\"; write \"}}\" for a brace"
+        );
+        assert_eq!(error("{#verses}{text}"), "{verses} is never closed");
+        assert_eq!(error("{?votd}verse of the day"), "{votd} is never closed");
+        assert_eq!(
+            error("{?votd}{/cached}"),
+            "expected {/votd}, found {/cached}"
+        );
+        assert_eq!(error("{/verses}"), "{/verses} closes nothing");
+        assert_eq!(
+            error("{verse}"),
+            "\"verse\" can only be used inside {#verses}"
+        );
+        assert_eq!(
+            error("{#text}{/text}"),
+            "can't repeat \"text\"; only \"verses\" can be"
+        );
+        assert_eq!(
+            error("{#verses}{#verses}{/verses}{/verses}"),
+            "{#verses} can't be nested"
+        );
+    }
+}
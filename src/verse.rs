@@ -0,0 +1,189 @@
+use crate::books;
+use crate::markup::StyleSpan;
+use crate::versification::{Canon, VerseId};
+use serde_derive::{Deserialize, Serialize};
+use std::fmt::Write;
+
+/// The text of a single verse.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct VerseText {
+    /// The name of the book, e.g. "John".
+    pub book: String,
+    pub chapter: u32,
+    pub verse: u32,
+    /// The text of the verse, without any markup.
+    pub text: String,
+    /// Emphasis within `text`, as marked up by the source.
+    #[serde(default)]
+    pub styles: Vec<StyleSpan>,
+    /// Section headings (or a psalm's superscription) that come before the
+    /// verse, as given by the source.
+    #[serde(default)]
+    pub headings: Vec<String>,
+}
+
+/// A passage of one or more verses, as returned by [`VerseClient`]. The
+/// verses needn't be contiguous, or even from the same book.
+///
+/// [`VerseClient`]: crate::VerseClient
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Verse {
+    /// Every verse in the passage, in the order they were requested.
+    pub verses: Vec<VerseText>,
+}
+
+impl VerseText {
+    /// Whether `next` directly follows this verse in the same book, going by
+    /// the versification of the first canon that has the book. For book
+    /// names that aren't recognized, a chapter is assumed to end wherever
+    /// the next one starts.
+    fn is_followed_by(&self, next: &VerseText) -> bool {
+        if self.book != next.book {
+            return false;
+        }
+        let known = books::find(&self.book).and_then(|book| {
+            let canon = Canon::ALL.iter().find(|canon| canon.contains(book))?;
+            Some((book, *canon))
+        });
+        match known {
+            Some((book, canon)) => {
+                let id = |verse: &VerseText| VerseId {
+                    book,
+                    chapter: verse.chapter,
+                    verse: verse.verse,
+                };
+                canon.next_verse(id(self)) == Some(id(next))
+            }
+            None => {
+                (next.chapter == self.chapter && next.verse == self.verse + 1)
+                    || (next.chapter == self.chapter + 1 && next.verse == 1)
+            }
+        }
+    }
+}
+
+impl Verse {
+    /// The reference of the passage, e.g. "John 3:16-17", "Genesis
+    /// 1:30-2:3", or "John 3:16, 18; Romans 8:28".
+    pub fn title(&self) -> String {
+        // Split the verses into runs of consecutive verses.
+        let mut runs: Vec<(&VerseText, &VerseText)> = Vec::new();
+        for verse in &self.verses {
+            match runs.last_mut() {
+                Some((_, end)) if end.is_followed_by(verse) => *end = verse,
+                _ => runs.push((verse, verse)),
+            }
+        }
+
+        let mut title = String::new();
+        let mut previous: Option<&VerseText> = None;
+        for (start, end) in runs {
+            match previous {
+                Some(previous)
+                    if previous.book == start.book && previous.chapter == start.chapter =>
+                {
+                    write!(title, ", {}", start.verse)
+                }
+                Some(previous) if previous.book == start.book => {
+                    write!(title, "; {}:{}", start.chapter, start.verse)
+                }
+                Some(_) => write!(title, "; {} {}:{}", start.book, start.chapter, start.verse),
+                None => write!(title, "{} {}:{}", start.book, start.chapter, start.verse),
+            }
+            .expect("writing to a String can't fail");
+            if end.chapter != start.chapter {
+                write!(title, "-{}:{}", end.chapter, end.verse)
+            } else if end.verse != start.verse {
+                write!(title, "-{}", end.verse)
+            } else {
+                Ok(())
+            }
+            .expect("writing to a String can't fail");
+            previous = Some(end);
+        }
+        title
+    }
+
+    /// The text of every verse in the passage, separated by spaces.
+    pub fn text(&self) -> String {
+        self.verses
+            .iter()
+            .map(|verse| verse.text.trim())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn passage(verses: &[(&str, u32, u32)]) -> Verse {
+        Verse {
+            verses: verses
+                .iter()
+                .map(|&(book, chapter, verse)| VerseText {
+                    book: book.to_owned(),
+                    chapter,
+                    verse,
+                    text: String::new(),
+                    styles: Vec::new(),
+                    headings: Vec::new(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn titles_single_verses_and_ranges() {
+        assert_eq!(passage(&[("John", 3, 16)]).title(), "John 3:16");
+        assert_eq!(
+            passage(&[("John", 3, 16), ("John", 3, 17)]).title(),
+            "John 3:16-17"
+        );
+    }
+
+    #[test]
+    fn titles_ranges_across_chapters() {
+        // Genesis 1 has 31 verses.
+        assert_eq!(
+            passage(&[("Genesis", 1, 30), ("Genesis", 1, 31), ("Genesis", 2, 1)]).title(),
+            "Genesis 1:30-2:1"
+        );
+    }
+
+    #[test]
+    fn titles_disjoint_chapters_separately() {
+        // John 3 has 36 verses, so 4:1 doesn't follow 3:16.
+        assert_eq!(
+            passage(&[("John", 3, 16), ("John", 4, 1)]).title(),
+            "John 3:16; 4:1"
+        );
+        assert_eq!(
+            passage(&[("John", 3, 16), ("John", 3, 18)]).title(),
+            "John 3:16, 18"
+        );
+    }
+
+    #[test]
+    fn titles_across_books() {
+        // John 21:25 is the last verse of John, but a range can't name two
+        // books.
+        assert_eq!(
+            passage(&[("John", 21, 25), ("Acts", 1, 1)]).title(),
+            "John 21:25; Acts 1:1"
+        );
+        assert_eq!(
+            passage(&[("John", 3, 16), ("Romans", 8, 28), ("Romans", 8, 29)]).title(),
+            "John 3:16; Romans 8:28-29"
+        );
+    }
+
+    #[test]
+    fn titles_unknown_books_by_guessing_chapter_ends() {
+        assert_eq!(
+            passage(&[("Unknown", 3, 16), ("Unknown", 4, 1)]).title(),
+            "Unknown 3:16-4:1"
+        );
+    }
+}
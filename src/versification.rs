@@ -0,0 +1,702 @@
+//! Chapter and verse counts for each book, so references can be checked and
+//! walked verse by verse without asking a provider.
+//!
+//! Counts follow the versification of most English Bibles. The Catholic and
+//! Orthodox canons add the deuterocanonical books, and number the Greek
+//! additions to Esther and Daniel as extra chapters (Esther 11-16, Daniel
+//! 13-14, and a longer Daniel 3); the Orthodox canon also adds Psalm 151.
+
+use crate::books::{self, Book};
+use std::fmt;
+use std::str::FromStr;
+
+/// Which books make up the Bible, and how they're divided.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Canon {
+    /// The 66 books of the Protestant canon.
+    #[default]
+    Protestant,
+    /// The Protestant canon plus Tobit, Judith, Wisdom, Sirach, Baruch, 1-2
+    /// Maccabees, and the Greek additions to Esther and Daniel.
+    Catholic,
+    /// The Catholic canon plus 1 Esdras, the Prayer of Manasseh, 3-4
+    /// Maccabees, and Psalm 151.
+    Orthodox,
+}
+
+/// A single verse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VerseId {
+    pub book: &'static Book,
+    pub chapter: u32,
+    pub verse: u32,
+}
+
+impl fmt::Display for VerseId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}:{}", self.book.name, self.chapter, self.verse)
+    }
+}
+
+/// The number of verses in each chapter, keyed by OSIS identifier.
+const PROTESTANT_VERSES: &[(&str, &[u32])] = &[
+    (
+        "Gen",
+        &[
+            31, 25, 24, 26, 32, 22, 24, 22, 29, 32, 32, 20, 18, 24, 21, 16, 27, 33, 38, 18, 34, 24,
+            20, 67, 34, 35, 46, 22, 35, 43, 55, 32, 20, 31, 29, 43, 36, 30, 23, 23, 57, 38, 34, 34,
+            28, 34, 31, 22, 33, 26,
+        ],
+    ),
+    (
+        "Exod",
+        &[
+            22, 25, 22, 31, 23, 30, 25, 32, 35, 29, 10, 51, 22, 31, 27, 36, 16, 27, 25, 26, 36, 31,
+            33, 18, 40, 37, 21, 43, 46, 38, 18, 35, 23, 35, 35, 38, 29, 31, 43, 38,
+        ],
+    ),
+    (
+        "Lev",
+        &[
+            17, 16, 17, 35, 19, 30, 38, 36, 24, 20, 47, 8, 59, 57, 33, 34, 16, 30, 37, 27, 24, 33,
+            44, 23, 55, 46, 34,
+        ],
+    ),
+    (
+        "Num",
+        &[
+            54, 34, 51, 49, 31, 27, 89, 26, 23, 36, 35, 16, 33, 45, 41, 50, 13, 32, 22, 29, 35, 41,
+            30, 25, 18, 65, 23, 31, 40, 16, 54, 42, 56, 29, 34, 13,
+        ],
+    ),
+    (
+        "Deut",
+        &[
+            46, 37, 29, 49, 33, 25, 26, 20, 29, 22, 32, 32, 18, 29, 23, 22, 20, 22, 21, 20, 23, 30,
+            25, 22, 19, 19, 26, 68, 29, 20, 30, 52, 29, 12,
+        ],
+    ),
+    (
+        "Josh",
+        &[
+            18, 24, 17, 24, 15, 27, 26, 35, 27, 43, 23, 24, 33, 15, 63, 10, 18, 28, 51, 9, 45, 34,
+            16, 33,
+        ],
+    ),
+    (
+        "Judg",
+        &[
+            36, 23, 31, 24, 31, 40, 25, 35, 57, 18, 40, 15, 25, 20, 20, 31, 13, 31, 30, 48, 25,
+        ],
+    ),
+    ("Ruth", &[22, 23, 18, 22]),
+    (
+        "1Sam",
+        &[
+            28, 36, 21, 22, 12, 21, 17, 22, 27, 27, 15, 25, 23, 52, 35, 23, 58, 30, 24, 42, 15, 23,
+            29, 22, 44, 25, 12, 25, 11, 31, 13,
+        ],
+    ),
+    (
+        "2Sam",
+        &[
+            27, 32, 39, 12, 25, 23, 29, 18, 13, 19, 27, 31, 39, 33, 37, 23, 29, 33, 43, 26, 22, 51,
+            39, 25,
+        ],
+    ),
+    (
+        "1Kgs",
+        &[
+            53, 46, 28, 34, 18, 38, 51, 66, 28, 29, 43, 33, 34, 31, 34, 34, 24, 46, 21, 43, 29, 53,
+        ],
+    ),
+    (
+        "2Kgs",
+        &[
+            18, 25, 27, 44, 27, 33, 20, 29, 37, 36, 21, 21, 25, 29, 38, 20, 41, 37, 37, 21, 26, 20,
+            37, 20, 30,
+        ],
+    ),
+    (
+        "1Chr",
+        &[
+            54, 55, 24, 43, 26, 81, 40, 40, 44, 14, 47, 40, 14, 17, 29, 43, 27, 17, 19, 8, 30, 19,
+            32, 31, 31, 32, 34, 21, 30,
+        ],
+    ),
+    (
+        "2Chr",
+        &[
+            17, 18, 17, 22, 14, 42, 22, 18, 31, 19, 23, 16, 22, 15, 19, 14, 19, 34, 11, 37, 20, 12,
+            21, 27, 28, 23, 9, 27, 36, 27, 21, 33, 25, 33, 27, 23,
+        ],
+    ),
+    ("Ezra", &[11, 70, 13, 24, 17, 22, 28, 36, 15, 44]),
+    ("Neh", &[11, 20, 32, 23, 19, 19, 73, 18, 38, 39, 36, 47, 31]),
+    ("Esth", &[22, 23, 15, 17, 14, 14, 10, 17, 32, 3]),
+    (
+        "Job",
+        &[
+            22, 13, 26, 21, 27, 30, 21, 22, 35, 22, 20, 25, 28, 22, 35, 22, 16, 21, 29, 29, 34, 30,
+            17, 25, 6, 14, 23, 28, 25, 31, 40, 22, 33, 37, 16, 33, 24, 41, 30, 24, 34, 17,
+        ],
+    ),
+    (
+        "Ps",
+        &[
+            6, 12, 8, 8, 12, 10, 17, 9, 20, 18, 7, 8, 6, 7, 5, 11, 15, 50, 14, 9, 13, 31, 6, 10,
+            22, 12, 14, 9, 11, 12, 24, 11, 22, 22, 28, 12, 40, 22, 13, 17, 13, 11, 5, 26, 17, 11,
+            9, 14, 20, 23, 19, 9, 6, 7, 23, 13, 11, 11, 17, 12, 8, 12, 11, 10, 13, 20, 7, 35, 36,
+            5, 24, 20, 28, 23, 10, 12, 20, 72, 13, 19, 16, 8, 18, 12, 13, 17, 7, 18, 52, 17, 16,
+            15, 5, 23, 11, 13, 12, 9, 9, 5, 8, 28, 22, 35, 45, 48, 43, 13, 31, 7, 10, 10, 9, 8, 18,
+            19, 2, 29, 176, 7, 8, 9, 4, 8, 5, 6, 5, 6, 8, 8, 3, 18, 3, 3, 21, 26, 9, 8, 24, 13, 10,
+            7, 12, 15, 21, 10, 20, 14, 9, 6,
+        ],
+    ),
+    (
+        "Prov",
+        &[
+            33, 22, 35, 27, 23, 35, 27, 36, 18, 32, 31, 28, 25, 35, 33, 33, 28, 24, 29, 30, 31, 29,
+            35, 34, 28, 28, 27, 28, 27, 33, 31,
+        ],
+    ),
+    ("Eccl", &[18, 26, 22, 16, 20, 12, 29, 17, 18, 20, 10, 14]),
+    ("Song", &[17, 17, 11, 16, 16, 13, 13, 14]),
+    (
+        "Isa",
+        &[
+            31, 22, 26, 6, 30, 13, 25, 22, 21, 34, 16, 6, 22, 32, 9, 14, 14, 7, 25, 6, 17, 25, 18,
+            23, 12, 21, 13, 29, 24, 33, 9, 20, 24, 17, 10, 22, 38, 22, 8, 31, 29, 25, 28, 28, 25,
+            13, 15, 22, 26, 11, 23, 15, 12, 17, 13, 12, 21, 14, 21, 22, 11, 12, 19, 12, 25, 24,
+        ],
+    ),
+    (
+        "Jer",
+        &[
+            19, 37, 25, 31, 31, 30, 34, 22, 26, 25, 23, 17, 27, 22, 21, 21, 27, 23, 15, 18, 14, 30,
+            40, 10, 38, 24, 22, 17, 32, 24, 40, 44, 26, 22, 19, 32, 21, 28, 18, 16, 18, 22, 13, 30,
+            5, 28, 7, 47, 39, 46, 64, 34,
+        ],
+    ),
+    ("Lam", &[22, 22, 66, 22, 22]),
+    (
+        "Ezek",
+        &[
+            28, 10, 27, 17, 17, 14, 27, 18, 11, 22, 25, 28, 23, 23, 8, 63, 24, 32, 14, 49, 32, 31,
+            49, 27, 17, 21, 36, 26, 21, 26, 18, 32, 33, 31, 15, 38, 28, 23, 29, 49, 26, 20, 27, 31,
+            25, 24, 23, 35,
+        ],
+    ),
+    ("Dan", &[21, 49, 30, 37, 31, 28, 28, 27, 27, 21, 45, 13]),
+    (
+        "Hos",
+        &[11, 23, 5, 19, 15, 11, 16, 14, 17, 15, 12, 14, 16, 9],
+    ),
+    ("Joel", &[20, 32, 21]),
+    ("Amos", &[15, 16, 15, 13, 27, 14, 17, 14, 15]),
+    ("Obad", &[21]),
+    ("Jonah", &[17, 10, 10, 11]),
+    ("Mic", &[16, 13, 12, 13, 15, 16, 20]),
+    ("Nah", &[15, 13, 19]),
+    ("Hab", &[17, 20, 19]),
+    ("Zeph", &[18, 15, 20]),
+    ("Hag", &[15, 23]),
+    (
+        "Zech",
+        &[21, 13, 10, 14, 11, 15, 14, 23, 17, 12, 17, 14, 9, 21],
+    ),
+    ("Mal", &[14, 17, 18, 6]),
+    (
+        "Matt",
+        &[
+            25, 23, 17, 25, 48, 34, 29, 34, 38, 42, 30, 50, 58, 36, 39, 28, 27, 35, 30, 34, 46, 46,
+            39, 51, 46, 75, 66, 20,
+        ],
+    ),
+    (
+        "Mark",
+        &[
+            45, 28, 35, 41, 43, 56, 37, 38, 50, 52, 33, 44, 37, 72, 47, 20,
+        ],
+    ),
+    (
+        "Luke",
+        &[
+            80, 52, 38, 44, 39, 49, 50, 56, 62, 42, 54, 59, 35, 35, 32, 31, 37, 43, 48, 47, 38, 71,
+            56, 53,
+        ],
+    ),
+    (
+        "John",
+        &[
+            51, 25, 36, 54, 47, 71, 53, 59, 41, 42, 57, 50, 38, 31, 27, 33, 26, 40, 42, 31, 25,
+        ],
+    ),
+    (
+        "Acts",
+        &[
+            26, 47, 26, 37, 42, 15, 60, 40, 43, 48, 30, 25, 52, 28, 41, 40, 34, 28, 41, 38, 40, 30,
+            35, 27, 27, 32, 44, 31,
+        ],
+    ),
+    (
+        "Rom",
+        &[
+            32, 29, 31, 25, 21, 23, 25, 39, 33, 21, 36, 21, 14, 23, 33, 27,
+        ],
+    ),
+    (
+        "1Cor",
+        &[
+            31, 16, 23, 21, 13, 20, 40, 13, 27, 33, 34, 31, 13, 40, 58, 24,
+        ],
+    ),
+    (
+        "2Cor",
+        &[24, 17, 18, 18, 21, 18, 16, 24, 15, 18, 33, 21, 14],
+    ),
+    ("Gal", &[24, 21, 29, 31, 26, 18]),
+    ("Eph", &[23, 22, 21, 32, 33, 24]),
+    ("Phil", &[30, 30, 21, 23]),
+    ("Col", &[29, 23, 25, 18]),
+    ("1Thess", &[10, 20, 13, 18, 28]),
+    ("2Thess", &[12, 17, 18]),
+    ("1Tim", &[20, 15, 16, 16, 25, 21]),
+    ("2Tim", &[18, 26, 17, 22]),
+    ("Titus", &[16, 15, 15]),
+    ("Phlm", &[25]),
+    ("Heb", &[14, 18, 19, 16, 14, 20, 28, 13, 28, 39, 40, 29, 25]),
+    ("Jas", &[27, 26, 18, 17, 20]),
+    ("1Pet", &[25, 25, 22, 19, 14]),
+    ("2Pet", &[21, 22, 18]),
+    ("1John", &[10, 29, 24, 21, 21]),
+    ("2John", &[13]),
+    ("3John", &[14]),
+    ("Jude", &[25]),
+    (
+        "Rev",
+        &[
+            20, 29, 22, 11, 14, 17, 17, 13, 21, 11, 19, 17, 18, 20, 8, 21, 18, 24, 21, 15, 27, 21,
+        ],
+    ),
+];
+
+const DEUTEROCANONICAL_VERSES: &[(&str, &[u32])] = &[
+    (
+        "Tob",
+        &[22, 14, 17, 21, 22, 17, 18, 21, 6, 12, 19, 22, 18, 15],
+    ),
+    (
+        "Jdt",
+        &[
+            16, 28, 10, 15, 24, 21, 32, 36, 14, 23, 23, 20, 20, 19, 13, 25,
+        ],
+    ),
+    (
+        "Wis",
+        &[
+            16, 24, 19, 20, 23, 25, 30, 21, 18, 21, 26, 27, 19, 31, 19, 29, 21, 25, 22,
+        ],
+    ),
+    (
+        "Sir",
+        &[
+            30, 18, 31, 31, 15, 37, 36, 19, 18, 31, 34, 18, 26, 27, 20, 30, 32, 33, 30, 32, 28, 27,
+            28, 34, 26, 29, 30, 26, 28, 25, 31, 24, 31, 26, 20, 26, 31, 34, 35, 30, 24, 25, 33, 23,
+            26, 20, 25, 25, 16, 29, 30,
+        ],
+    ),
+    ("Bar", &[22, 35, 37, 37, 9, 73]),
+    (
+        "1Macc",
+        &[
+            64, 70, 60, 61, 68, 63, 50, 32, 73, 89, 74, 53, 53, 49, 41, 24,
+        ],
+    ),
+    (
+        "2Macc",
+        &[36, 32, 40, 50, 27, 31, 42, 36, 29, 38, 38, 45, 26, 46, 39],
+    ),
+    ("1Esd", &[58, 31, 24, 63, 71, 34, 15, 96, 55]),
+    ("PrMan", &[15]),
+    ("3Macc", &[29, 33, 30, 21, 51, 41, 23]),
+    (
+        "4Macc",
+        &[
+            35, 24, 21, 26, 38, 35, 23, 29, 32, 21, 27, 19, 27, 20, 32, 25, 24, 24,
+        ],
+    ),
+];
+
+/// Esther with the Greek additions, as numbered in the Vulgate.
+const GREEK_ESTHER_VERSES: &[u32] = &[
+    22, 23, 15, 17, 14, 14, 10, 17, 32, 13, 12, 6, 18, 19, 16, 24,
+];
+
+/// Daniel with the Prayer of Azariah, Susanna, and Bel and the Dragon.
+const GREEK_DANIEL_VERSES: &[u32] = &[21, 49, 100, 34, 30, 29, 28, 27, 27, 21, 45, 13, 64, 42];
+
+const PSALM_151_VERSES: u32 = 7;
+
+/// Where the deuterocanonical books go in each canon's order: each group is
+/// placed after the book named first.
+const CATHOLIC_ORDER: &[(&str, &[&str])] = &[
+    ("Neh", &["Tob", "Jdt"]),
+    ("Esth", &["1Macc", "2Macc"]),
+    ("Song", &["Wis", "Sir"]),
+    ("Lam", &["Bar"]),
+];
+const ORTHODOX_ORDER: &[(&str, &[&str])] = &[
+    ("2Chr", &["PrMan", "1Esd"]),
+    ("Neh", &["Tob", "Jdt"]),
+    ("Esth", &["1Macc", "2Macc", "3Macc", "4Macc"]),
+    ("Song", &["Wis", "Sir"]),
+    ("Lam", &["Bar"]),
+];
+
+/// The names accepted when parsing a [`Canon`].
+pub const CANON_NAMES: &[&str] = &["protestant", "catholic", "orthodox"];
+
+impl Canon {
+    /// Every canon, smallest first.
+    pub const ALL: [Canon; 3] = [Canon::Protestant, Canon::Catholic, Canon::Orthodox];
+
+    /// The books of this canon, in order.
+    pub fn books(self) -> Vec<&'static Book> {
+        let order = match self {
+            Canon::Protestant => return books::BOOKS.iter().collect(),
+            Canon::Catholic => CATHOLIC_ORDER,
+            Canon::Orthodox => ORTHODOX_ORDER,
+        };
+        let mut result = Vec::new();
+        for book in books::BOOKS {
+            result.push(book);
+            if let Some((_, inserted)) = order.iter().find(|(after, _)| *after == book.osis) {
+                result.extend(inserted.iter().filter_map(|osis| books::find(osis)));
+            }
+        }
+        result
+    }
+
+    /// Whether `book` is part of this canon.
+    pub fn contains(self, book: &Book) -> bool {
+        match self {
+            Canon::Protestant => books::BOOKS.contains(book),
+            Canon::Catholic => {
+                books::BOOKS.contains(book)
+                    || CATHOLIC_ORDER
+                        .iter()
+                        .any(|(_, inserted)| inserted.contains(&book.osis))
+            }
+            Canon::Orthodox => books::all().any(|known| known == book),
+        }
+    }
+
+    /// The number of verses in each chapter of `book`, or `None` if the book
+    /// isn't in this canon. Psalm 151 is left out; see [`Canon::verses`].
+    fn verse_counts(self, book: &Book) -> Option<&'static [u32]> {
+        if !self.contains(book) {
+            return None;
+        }
+        if self != Canon::Protestant {
+            match book.osis {
+                "Esth" => return Some(GREEK_ESTHER_VERSES),
+                "Dan" => return Some(GREEK_DANIEL_VERSES),
+                _ => {}
+            }
+        }
+        PROTESTANT_VERSES
+            .iter()
+            .chain(DEUTEROCANONICAL_VERSES)
+            .find(|(osis, _)| *osis == book.osis)
+            .map(|(_, counts)| *counts)
+    }
+
+    fn has_psalm_151(self, book: &Book) -> bool {
+        self == Canon::Orthodox && book.osis == "Ps"
+    }
+
+    /// The number of chapters in `book`, or `None` if it isn't in this canon.
+    pub fn chapters(self, book: &Book) -> Option<u32> {
+        let counts = self.verse_counts(book)?;
+        Some(counts.len() as u32 + u32::from(self.has_psalm_151(book)))
+    }
+
+    /// The number of verses in a chapter, or `None` if it doesn't exist in
+    /// this canon.
+    pub fn verses(self, book: &Book, chapter: u32) -> Option<u32> {
+        let counts = self.verse_counts(book)?;
+        if chapter == 0 {
+            return None;
+        }
+        match counts.get(chapter as usize - 1) {
+            Some(&count) => Some(count),
+            None if self.has_psalm_151(book) && chapter as usize == counts.len() + 1 => {
+                Some(PSALM_151_VERSES)
+            }
+            None => None,
+        }
+    }
+
+    /// Whether `verse` exists in this canon.
+    pub fn contains_verse(self, verse: VerseId) -> bool {
+        verse.verse >= 1
+            && self
+                .verses(verse.book, verse.chapter)
+                .map_or(false, |count| verse.verse <= count)
+    }
+
+    /// The first and last verses of a chapter.
+    pub fn chapter_bounds(self, book: &'static Book, chapter: u32) -> Option<(VerseId, VerseId)> {
+        let count = self.verses(book, chapter)?;
+        Some((
+            VerseId {
+                book,
+                chapter,
+                verse: 1,
+            },
+            VerseId {
+                book,
+                chapter,
+                verse: count,
+            },
+        ))
+    }
+
+    fn neighbouring_book(self, book: &Book, forwards: bool) -> Option<&'static Book> {
+        let books = self.books();
+        let position = books.iter().position(|known| *known == book)?;
+        if forwards {
+            books.get(position + 1).copied()
+        } else {
+            position.checked_sub(1).map(|previous| books[previous])
+        }
+    }
+
+    /// The chapter after `chapter`, moving on to the next book after the last
+    /// chapter of `book`.
+    pub fn next_chapter(self, book: &'static Book, chapter: u32) -> Option<(&'static Book, u32)> {
+        if chapter < self.chapters(book)? {
+            Some((book, chapter + 1))
+        } else {
+            Some((self.neighbouring_book(book, true)?, 1))
+        }
+    }
+
+    /// The chapter before `chapter`, moving back to the previous book before
+    /// the first chapter of `book`.
+    pub fn previous_chapter(
+        self,
+        book: &'static Book,
+        chapter: u32,
+    ) -> Option<(&'static Book, u32)> {
+        if chapter > 1 {
+            Some((book, chapter - 1))
+        } else {
+            let previous = self.neighbouring_book(book, false)?;
+            Some((previous, self.chapters(previous)?))
+        }
+    }
+
+    /// The verse after `verse`, crossing into the next chapter or book as
+    /// needed.
+    pub fn next_verse(self, verse: VerseId) -> Option<VerseId> {
+        if verse.verse < self.verses(verse.book, verse.chapter)? {
+            return Some(VerseId {
+                verse: verse.verse + 1,
+                ..verse
+            });
+        }
+        let (book, chapter) = self.next_chapter(verse.book, verse.chapter)?;
+        Some(self.chapter_bounds(book, chapter)?.0)
+    }
+
+    /// The verse before `verse`, crossing into the previous chapter or book
+    /// as needed.
+    pub fn previous_verse(self, verse: VerseId) -> Option<VerseId> {
+        if verse.verse > 1 {
+            return Some(VerseId {
+                verse: verse.verse - 1,
+                ..verse
+            });
+        }
+        let (book, chapter) = self.previous_chapter(verse.book, verse.chapter)?;
+        Some(self.chapter_bounds(book, chapter)?.1)
+    }
+
+    /// The canons, other than this one, in which a chapter exists; used to
+    /// suggest switching canons when a reference isn't valid in this one.
+    pub fn others_with_chapter(self, book: &Book, chapter: u32) -> Vec<Canon> {
+        Canon::ALL
+            .iter()
+            .copied()
+            .filter(|canon| *canon != self && canon.verses(book, chapter).is_some())
+            .collect()
+    }
+}
+
+impl fmt::Display for Canon {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Canon::Protestant => "Protestant",
+            Canon::Catholic => "Catholic",
+            Canon::Orthodox => "Orthodox",
+        })
+    }
+}
+
+impl FromStr for Canon {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "protestant" => Ok(Canon::Protestant),
+            "catholic" => Ok(Canon::Catholic),
+            "orthodox" => Ok(Canon::Orthodox),
+            _ => Err(format!(
+                "unknown canon {:?}; expected one of: {}",
+                s,
+                CANON_NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reference::{Reference, ReferenceError};
+
+    fn verse(book: &str, chapter: u32, verse: u32) -> VerseId {
+        VerseId {
+            book: books::find(book).unwrap(),
+            chapter,
+            verse,
+        }
+    }
+
+    #[test]
+    fn rejects_chapters_the_protestant_canon_lacks() {
+        assert_eq!(
+            "Jude 2:1".parse::<Reference>(),
+            Err(ReferenceError::ChapterOutOfRange {
+                book: "Jude",
+                chapter: 2,
+                chapters: 1,
+                other_canons: vec![],
+            })
+        );
+        assert_eq!(
+            "Psalm 151".parse::<Reference>(),
+            Err(ReferenceError::ChapterOutOfRange {
+                book: "Psalms",
+                chapter: 151,
+                chapters: 150,
+                other_canons: vec![Canon::Orthodox],
+            })
+        );
+        assert!(matches!(
+            "Daniel 13".parse::<Reference>(),
+            Err(ReferenceError::ChapterOutOfRange { .. })
+        ));
+        assert!(matches!(
+            "Jude 30".parse::<Reference>(),
+            Err(ReferenceError::VerseOutOfRange { verses: 25, .. })
+        ));
+    }
+
+    #[test]
+    fn psalm_151_is_only_in_the_orthodox_canon() {
+        let psalms = books::find("Psalms").unwrap();
+        assert_eq!(Canon::Protestant.chapters(psalms), Some(150));
+        assert_eq!(Canon::Catholic.verses(psalms, 151), None);
+        assert_eq!(Canon::Orthodox.chapters(psalms), Some(151));
+        assert_eq!(Canon::Orthodox.verses(psalms, 151), Some(7));
+        assert!(Reference::parse_with("Psalm 151:1-7", Canon::Orthodox).is_ok());
+    }
+
+    #[test]
+    fn catholic_canon_has_the_greek_additions() {
+        assert_eq!(
+            Canon::Catholic.chapters(books::find("Daniel").unwrap()),
+            Some(14)
+        );
+        assert_eq!(
+            Canon::Catholic.chapters(books::find("Esther").unwrap()),
+            Some(16)
+        );
+        for input in ["Daniel 13-14", "Daniel 3:100", "Esther 11-16", "Tobit 1:1"] {
+            assert!(
+                Reference::parse_with(input, Canon::Catholic).is_ok(),
+                "{:?}",
+                input
+            );
+        }
+        assert!(matches!(
+            "Tobit 1:1".parse::<Reference>(),
+            Err(ReferenceError::NotInCanon { other_canons, .. })
+                if other_canons == [Canon::Catholic, Canon::Orthodox]
+        ));
+    }
+
+    #[test]
+    fn canons_order_their_books() {
+        assert_eq!(Canon::Protestant.books().len(), 66);
+        assert_eq!(Canon::Catholic.books().len(), 73);
+        let catholic: Vec<&str> = Canon::Catholic.books().iter().map(|b| b.osis).collect();
+        let nehemiah = catholic.iter().position(|&osis| osis == "Neh").unwrap();
+        assert_eq!(catholic[nehemiah + 1..nehemiah + 4], ["Tob", "Jdt", "Esth"]);
+        for canon in Canon::ALL {
+            for book in canon.books() {
+                assert!(canon.contains(book));
+                assert!(canon.chapters(book).unwrap() >= book.chapters);
+            }
+        }
+    }
+
+    #[test]
+    fn walks_across_chapters_and_books() {
+        let canon = Canon::Protestant;
+        assert_eq!(
+            canon.next_verse(verse("John", 3, 16)),
+            Some(verse("John", 3, 17))
+        );
+        assert_eq!(
+            canon.next_verse(verse("John", 3, 36)),
+            Some(verse("John", 4, 1))
+        );
+        assert_eq!(
+            canon.previous_verse(verse("John", 4, 1)),
+            Some(verse("John", 3, 36))
+        );
+        assert_eq!(
+            canon.next_verse(verse("Malachi", 4, 6)),
+            Some(verse("Matthew", 1, 1))
+        );
+        assert_eq!(
+            canon.previous_verse(verse("Matthew", 1, 1)),
+            Some(verse("Malachi", 4, 6))
+        );
+        // Books the canon adds are walked through in its order.
+        assert_eq!(
+            Canon::Catholic.next_verse(verse("Nehemiah", 13, 31)),
+            Some(verse("Tobit", 1, 1))
+        );
+        assert_eq!(
+            Canon::Orthodox.next_verse(verse("Psalms", 150, 6)),
+            Some(verse("Psalms", 151, 1))
+        );
+    }
+
+    #[test]
+    fn stops_at_the_ends_of_the_bible() {
+        for canon in Canon::ALL {
+            assert_eq!(canon.previous_verse(verse("Genesis", 1, 1)), None);
+            assert_eq!(canon.next_verse(verse("Revelation", 22, 21)), None);
+            assert_eq!(
+                canon.next_verse(verse("Revelation", 22, 20)),
+                Some(verse("Revelation", 22, 21))
+            );
+        }
+    }
+}
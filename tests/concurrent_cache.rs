@@ -0,0 +1,91 @@
+//! Many processes writing to one cache at once, as when several shells start
+//! together. Each child is this test binary run again, for just
+//! [`write_entries`], with the cache and its share of the keys passed in the
+//! environment.
+
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use votd::{CacheHealth, CacheKey, Verse, VerseCache, VerseText};
+
+const PROCESSES: u32 = 8;
+const ENTRIES_PER_PROCESS: u32 = 16;
+/// Enough room that nothing is evicted.
+const MAX_ENTRIES: usize = (PROCESSES * ENTRIES_PER_PROCESS) as usize;
+
+const CACHE_VAR: &str = "VOTD_TEST_CACHE";
+const PROCESS_VAR: &str = "VOTD_TEST_PROCESS";
+
+/// Each entry is a different verse of Psalm 119, which has enough for all
+/// of them.
+fn number(process: u32, entry: u32) -> u32 {
+    process * ENTRIES_PER_PROCESS + entry + 1
+}
+
+fn key(process: u32, entry: u32) -> CacheKey {
+    let reference = format!("Psalm 119:{}", number(process, entry));
+    CacheKey::passage("NET", &reference.parse().unwrap())
+}
+
+fn verse(process: u32, entry: u32) -> Verse {
+    Verse {
+        verses: vec![VerseText {
+            book: "Psalms".to_owned(),
+            chapter: 119,
+            verse: number(process, entry),
+            text: format!("Written by process {}", process),
+            styles: Vec::new(),
+            headings: Vec::new(),
+        }],
+    }
+}
+
+fn spawn(path: &Path, process: u32) -> Child {
+    Command::new(std::env::current_exe().unwrap())
+        .args(["write_entries", "--exact", "--quiet"])
+        .env(CACHE_VAR, path)
+        .env(PROCESS_VAR, process.to_string())
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap()
+}
+
+/// A child's work: each entry is saved on its own, from a cache opened
+/// before any of them, so every save has to merge in the other processes'.
+/// Does nothing when not run as a child.
+#[test]
+fn write_entries() {
+    let (path, process) = match (std::env::var_os(CACHE_VAR), std::env::var(PROCESS_VAR)) {
+        (Some(path), Ok(process)) => (path, process.parse().unwrap()),
+        _ => return,
+    };
+    let mut cache = VerseCache::open(Path::new(&path), MAX_ENTRIES).unwrap();
+    for entry in 0..ENTRIES_PER_PROCESS {
+        cache.insert(&key(process, entry), &verse(process, entry));
+        cache.save().unwrap();
+    }
+}
+
+#[test]
+fn concurrent_writers_keep_every_entry() {
+    if std::env::var_os(CACHE_VAR).is_some() {
+        return;
+    }
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("cache");
+    let children: Vec<Child> = (0..PROCESSES)
+        .map(|process| spawn(&path, process))
+        .collect();
+    for mut child in children {
+        assert!(child.wait().unwrap().success());
+    }
+
+    let mut cache = VerseCache::open(&path, MAX_ENTRIES).unwrap();
+    assert_eq!(cache.health(), CacheHealth::Ok);
+    assert_eq!(cache.len(), MAX_ENTRIES);
+    for process in 0..PROCESSES {
+        for entry in 0..ENTRIES_PER_PROCESS {
+            let cached = cache.get(&key(process, entry)).unwrap();
+            assert_eq!(cached.verse, verse(process, entry));
+        }
+    }
+}